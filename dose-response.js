@@ -95,7 +95,8 @@ const keymap = {
   "ArrowDown": 61,
   "Enter": 62,
   " ": 63,
-  "Escape": 64
+  "Escape": 64,
+  "?": 65
 };
 
 const numpad_keymap = {
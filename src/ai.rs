@@ -1,4 +1,4 @@
-use formula;
+use formula::Balance;
 use game::Action;
 use monster::Monster;
 use player::Mind;
@@ -9,7 +9,7 @@ use rect::Rectangle;
 use world::World;
 
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Behavior {
     LoneAttacker,
     PackAttacker,
@@ -17,7 +17,7 @@ pub enum Behavior {
 }
 
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum AIState {
     Idle,
     Chasing,
@@ -48,9 +48,10 @@ pub fn lone_attacker_act<R: Rng>(
     player_info: PlayerInfo,
     world: &mut World,
     rng: &mut R,
+    balance: &Balance,
 ) -> (Update, Action) {
     let distance = actor.position.tile_distance(player_info.pos);
-    let ai_state = if distance <= formula::CHASING_DISTANCE {
+    let ai_state = if distance <= balance.chasing_distance {
         AIState::Chasing
     } else {
         AIState::Idle
@@ -75,9 +76,10 @@ pub fn pack_attacker_act<R: Rng>(
     player_info: PlayerInfo,
     world: &mut World,
     rng: &mut R,
+    balance: &Balance,
 ) -> (Update, Action) {
     let player_distance = actor.position.tile_distance(player_info.pos);
-    let ai_state = if player_distance <= formula::CHASING_DISTANCE {
+    let ai_state = if player_distance <= balance.chasing_distance {
         AIState::Chasing
     } else if actor.ai_state == AIState::Chasing {
         AIState::Idle
@@ -90,7 +92,7 @@ pub fn pack_attacker_act<R: Rng>(
     let action = match ai_state {
         AIState::Chasing => {
             let howling_area =
-                Rectangle::center(actor.position, Point::from_i32(formula::HOWLING_DISTANCE));
+                Rectangle::center(actor.position, Point::from_i32(balance.howling_distance));
             let howlees = world.monsters(howling_area)
                 .filter(|m| {
                     m.behavior == Behavior::PackAttacker && m.position != actor.position
@@ -122,6 +124,7 @@ pub fn friendly_act<R: Rng>(
     player_info: PlayerInfo,
     world: &mut World,
     rng: &mut R,
+    balance: &Balance,
 ) -> (Update, Action) {
     let destination = if actor.accompanying_player {
         // Pick a position near the player
@@ -134,7 +137,7 @@ pub fn friendly_act<R: Rng>(
     let update = Update {
         ai_state: actor.ai_state,
         max_ap: if player_info.mind.is_high() {
-            formula::ESTRANGED_NPC_MAX_AP
+            balance.estranged_npc_max_ap
         } else {
             player_info.max_ap
         }
@@ -145,6 +148,51 @@ pub fn friendly_act<R: Rng>(
 }
 
 
+/// Where should the monster move during the coarse (off-screen)
+/// simulation step.
+///
+/// This is much cheaper than the full AI: there's no pathfinding and
+/// the monster moves by at most one tile.
+pub fn coarse_destination<R: Rng>(
+    actor: &Monster,
+    world: &World,
+    rng: &mut R,
+    player_position: Point,
+    balance: &Balance,
+) -> Point {
+    match actor.behavior {
+        Behavior::PackAttacker => {
+            // Packs regroup when they're scattered and roam about
+            // when they're together.
+            let howling_area =
+                Rectangle::center(actor.position, Point::from_i32(balance.howling_distance));
+            let nearest_pack_member = world.monsters(howling_area)
+                .filter(|m| {
+                    m.behavior == Behavior::PackAttacker && m.position != actor.position
+                })
+                .map(|m| m.position)
+                .min_by_key(|&pos| (actor.position.tile_distance(pos), pos.x, pos.y));
+            match nearest_pack_member {
+                Some(pos) if actor.position.tile_distance(pos) > 2 => {
+                    let direction = Point::new((pos.x - actor.position.x).signum(),
+                                               (pos.y - actor.position.y).signum());
+                    actor.position + direction
+                }
+                _ => world.random_neighbour_position(
+                    rng,
+                    actor.position,
+                    actor.blockers,
+                    player_position,
+                ),
+            }
+        }
+        Behavior::LoneAttacker | Behavior::Friendly => {
+            world.random_neighbour_position(rng, actor.position, actor.blockers, player_position)
+        }
+    }
+}
+
+
 fn idle_destination<R: Rng>(
     actor: &Monster,
     world: &World,
@@ -1,3 +1,6 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+
 bitflags! {
     /// Flag to indicate features that block pathfinding/walking.
     pub struct Blocker: u32 {
@@ -6,3 +9,45 @@ bitflags! {
         const PLAYER  = 0b00000100;
     }
 }
+
+
+/// A single `Blocker` flag as it's written in the data files and
+/// chunk cache: `["Wall", "Monster"]`.
+#[derive(Serialize, Deserialize)]
+enum Flag {
+    Wall,
+    Monster,
+    Player,
+}
+
+// NOTE: `bitflags` doesn't implement serde's traits so we store the
+// list of flags that are set.
+impl Serialize for Blocker {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut flags = vec![];
+        if self.contains(Blocker::WALL) {
+            flags.push(Flag::Wall);
+        }
+        if self.contains(Blocker::MONSTER) {
+            flags.push(Flag::Monster);
+        }
+        if self.contains(Blocker::PLAYER) {
+            flags.push(Flag::Player);
+        }
+        flags.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Blocker {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let flags = Vec::<Flag>::deserialize(deserializer)?;
+        let result = flags.iter().fold(Blocker::empty(), |result, flag| {
+            result | match *flag {
+                Flag::Wall => Blocker::WALL,
+                Flag::Monster => Blocker::MONSTER,
+                Flag::Player => Blocker::PLAYER,
+            }
+        });
+        Ok(result)
+    }
+}
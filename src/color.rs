@@ -1,26 +1,64 @@
 #![allow(non_upper_case_globals)]
 #![cfg_attr(rustfmt, rustfmt_skip)]
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
 }
 
+/// Colour adjustments applied to everything that's drawn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    Default,
+    /// Brighter foreground on darker backgrounds.
+    HighContrast,
+}
+
+impl Theme {
+    /// The theme to switch to when the player cycles through them.
+    pub fn next(self) -> Self {
+        match self {
+            Theme::Default => Theme::HighContrast,
+            Theme::HighContrast => Theme::Default,
+        }
+    }
+
+    pub fn foreground(self, color: Color) -> Color {
+        match self {
+            Theme::Default => color,
+            Theme::HighContrast => {
+                // NOTE: scale the colour up so its strongest channel is at the maximum
+                let max = ::std::cmp::max(color.r, ::std::cmp::max(color.g, color.b)) as u32;
+                if max == 0 {
+                    color
+                } else {
+                    let scale = |channel: u8| (channel as u32 * 255 / max) as u8;
+                    Color { r: scale(color.r), g: scale(color.g), b: scale(color.b) }
+                }
+            }
+        }
+    }
+
+    pub fn background(self, color: Color) -> Color {
+        match self {
+            Theme::Default => color,
+            Theme::HighContrast => Color { r: color.r / 2, g: color.g / 2, b: color.b / 2 },
+        }
+    }
+}
+
 pub static background: Color = Color { r: 0, g: 0, b: 0 };
 pub static dim_background: Color = Color { r: 30, g: 30, b: 30 };
 pub static death_animation: Color = Color { r: 255, g: 0, b: 0 };
 pub static overdose_animation: Color = Color { r: 255, g: 255, b: 255 };
 pub static exhaustion_animation: Color = Color { r: 0, g: 0, b: 0 };
 pub static gui_text: Color = Color { r: 255, g: 255, b: 255 };
+pub static gui_text_dim: Color = Color { r: 150, g: 150, b: 150 };
+pub static gui_warning: Color = Color { r: 255, g: 64, b: 64 };
 pub static gui_progress_bar_fg: Color = Color { r: 0, g: 255, b: 0 };
 pub static gui_progress_bar_bg: Color = Color { r: 20, g: 133, b: 20 };
-pub static anxiety: Color = Color { r: 191, g: 0, b: 0 };
-pub static depression: Color = Color { r: 111, g: 63, b: 255 };
-pub static hunger: Color = Color { r: 127, g: 101, b: 63 };
-pub static voices: Color = Color { r: 95, g: 95, b: 95 };
-pub static shadows: Color = Color { r: 95, g: 95, b: 95 };
 //pub static npc_golden: Color = Color { r: 193, g: 193, b: 68 };
 pub static npc_dim: Color = Color { r: 95, g: 95, b: 95 };
 pub static npc_will: Color = Color { r: 191, g: 0, b: 0 };
@@ -29,13 +67,13 @@ pub static npc_speed: Color = Color { r: 111, g: 63, b: 255 } ;
 pub static player: Color = Color { r: 255, g: 255, b: 255 };
 pub static dead_player: Color = Color { r: 80, g: 80, b: 80 };
 pub static empty_tile: Color = Color { r: 223, g: 223, b: 223 };
-pub static dose: Color = Color { r: 114, g: 126, b: 255 };
-pub static dose_glow: Color = Color { r: 15, g: 255, b: 243 };
-pub static shattering_dose: Color = Color { r: 15, g: 255, b: 243 };
+pub static pickaxe: Color = Color { r: 191, g: 191, b: 191 };
+pub static gun: Color = Color { r: 255, g: 191, b: 0 };
 pub static dose_background: Color = Color { r: 0, g: 64, b: 64 };
+pub static travel_path: Color = Color { r: 40, g: 40, b: 90 };
+pub static look_cursor: Color = Color { r: 140, g: 140, b: 40 };
 pub static explosion: Color = Color { r: 15, g: 255, b: 243 };
 pub static shattering_explosion: Color = Color { r: 191, g: 0, b: 0 };
-pub static food: Color = Color { r: 148, g: 113, b: 0 };
 pub static tree_1: Color = Color { r: 0, g: 191, b: 0 };
 pub static tree_2: Color = Color { r: 0, g: 255, b: 0 };
 pub static tree_3: Color = Color { r: 63, g: 255, b: 63 };
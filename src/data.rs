@@ -0,0 +1,411 @@
+use ai::Behavior;
+use blocker::Blocker;
+use color::Color;
+use item::{self, Explosion, Item};
+use monster;
+use player::Modifier;
+use ranged_int::InclusiveRange;
+
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+
+/// Directory (relative to the working directory) where the game
+/// looks for its data files. Any file that's not there falls back to
+/// the defaults built into the game.
+const DATA_DIRECTORY: &str = "data";
+
+const DEFAULT_MONSTERS: &str = include_str!("../data/monsters.json");
+const DEFAULT_ITEMS: &str = include_str!("../data/items.json");
+
+
+/// Read the `file_name` data file and check it with `validate`.
+///
+/// If the file doesn't exist, the `default` contents are used
+/// instead. An invalid file is a hard error: we'd rather tell the
+/// person editing it than silently play with something else.
+pub fn load<T, F>(file_name: &str, default: &str, validate: F) -> T
+where
+    T: DeserializeOwned,
+    F: Fn(&T) -> Result<(), String>,
+{
+    let path = Path::new(DATA_DIRECTORY).join(file_name);
+    let (source, contents) = if path.exists() {
+        let mut contents = String::new();
+        File::open(&path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .expect(&format!("Could not read the data file: '{}'.", path.display()));
+        (format!("'{}'", path.display()), contents)
+    } else {
+        (format!("the built-in '{}'", file_name), default.to_string())
+    };
+
+    let data = serde_json::from_str(&contents)
+        .unwrap_or_else(|err| panic!("Could not parse {}: {}", source, err));
+    if let Err(message) = validate(&data) {
+        panic!("Invalid data in {}: {}", source, message);
+    }
+    data
+}
+
+
+/// Everything a monster is created from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterArchetype {
+    pub name: String,
+    /// The game logic that's tied to the monster (e.g. Anxieties
+    /// increase Will when killed, NPCs can become companions).
+    pub kind: monster::Kind,
+    pub glyph: char,
+    pub color: Color,
+    pub max_ap: i32,
+    pub behavior: Behavior,
+    pub blockers: Blocker,
+    pub die_after_attack: bool,
+    pub invincible: bool,
+    pub attack: Modifier,
+    /// How likely is the monster to appear on an empty tile. It's
+    /// relative to the other archetypes and `no_monster_weight`.
+    pub spawn_weight: u32,
+}
+
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterArchetypes {
+    /// The weight of an empty tile not getting any monster.
+    pub no_monster_weight: u32,
+    pub archetypes: Vec<MonsterArchetype>,
+}
+
+impl MonsterArchetypes {
+    /// Load the archetypes from `data/monsters.json` or use the
+    /// built-in ones if the file doesn't exist.
+    pub fn load() -> Self {
+        load("monsters.json", DEFAULT_MONSTERS, MonsterArchetypes::validate)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.archetypes.is_empty() {
+            return Err("there must be at least one monster archetype".into());
+        }
+
+        let mut names = HashSet::new();
+        let mut total_weight = self.no_monster_weight;
+        for archetype in &self.archetypes {
+            if archetype.name.is_empty() {
+                return Err("every monster archetype must have a name".into());
+            }
+            if !names.insert(&archetype.name) {
+                return Err(format!("duplicate monster archetype '{}'", archetype.name));
+            }
+            if !archetype.glyph.is_ascii() || archetype.glyph.is_whitespace() {
+                return Err(format!(
+                    "the glyph of '{}' must be a visible ASCII character, got {:?}",
+                    archetype.name,
+                    archetype.glyph
+                ));
+            }
+            if archetype.max_ap < 1 {
+                return Err(format!(
+                    "'{}' must have at least 1 action point, got {}",
+                    archetype.name,
+                    archetype.max_ap
+                ));
+            }
+            total_weight = total_weight.checked_add(archetype.spawn_weight).ok_or(format!(
+                "the spawn weights overflow at '{}'",
+                archetype.name
+            ))?;
+        }
+
+        if total_weight == 0 {
+            return Err("the spawn weights must not all be zero".into());
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Everything an item is created from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemArchetype {
+    pub name: String,
+    pub glyph: char,
+    pub color: Color,
+    /// What happens to the player when they use the item. Items with
+    /// the `Intoxication` modifier are doses.
+    pub modifier: Modifier,
+    pub irresistible: i32,
+    /// Random change to the item's `state_of_mind` when it's
+    /// generated.
+    pub mind_variance: Option<InclusiveRange>,
+    pub explosion: Explosion,
+    /// The number key the item is used with from the inventory.
+    pub inventory_key: u8,
+    /// Whether the item is removed from the player's starting area
+    /// (see `Balance::no_lethal_dose_radius`).
+    pub excluded_from_start: bool,
+    /// How likely is the item to appear on an empty tile. It's
+    /// relative to the other archetypes and `no_item_weight`.
+    pub spawn_weight: u32,
+}
+
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemArchetypes {
+    /// The weight of an empty tile not getting any item.
+    pub no_item_weight: u32,
+    /// Name of the archetype placed near the start, respawned in the
+    /// off-screen world and given out by the cheat key.
+    pub food: String,
+    /// Name of the archetype of the dose placed near the start.
+    pub starting_dose: String,
+    pub archetypes: Vec<ItemArchetype>,
+}
+
+impl ItemArchetypes {
+    /// Load the archetypes from `data/items.json` or use the built-in
+    /// ones if the file doesn't exist.
+    pub fn load() -> Self {
+        load("items.json", DEFAULT_ITEMS, ItemArchetypes::validate)
+    }
+
+    pub fn kinds(&self) -> impl Iterator<Item = item::Kind> {
+        (0..self.archetypes.len()).map(item::Kind)
+    }
+
+    pub fn archetype(&self, kind: item::Kind) -> &ItemArchetype {
+        &self.archetypes[kind.0]
+    }
+
+    pub fn kind_by_name(&self, name: &str) -> Option<item::Kind> {
+        self.archetypes
+            .iter()
+            .position(|archetype| archetype.name == name)
+            .map(item::Kind)
+    }
+
+    pub fn kind_by_inventory_key(&self, key: u8) -> Option<item::Kind> {
+        self.archetypes
+            .iter()
+            .position(|archetype| archetype.inventory_key == key)
+            .map(item::Kind)
+    }
+
+    pub fn food(&self) -> item::Kind {
+        self.kind_by_name(&self.food).expect("The food archetype must exist.")
+    }
+
+    pub fn starting_dose(&self) -> item::Kind {
+        self.kind_by_name(&self.starting_dose).expect(
+            "The starting dose archetype must exist.",
+        )
+    }
+
+    /// How many items of the `kind` the generator puts on this many
+    /// empty tiles on average (rounded up).
+    pub fn expected_count(&self, kind: item::Kind, empty_tiles: u32) -> u32 {
+        let total_weight = self.archetypes
+            .iter()
+            .fold(self.no_item_weight as u64, |total, archetype| {
+                total + archetype.spawn_weight as u64
+            });
+        let weight = self.archetype(kind).spawn_weight as u64;
+        ((empty_tiles as u64 * weight + total_weight - 1) / total_weight) as u32
+    }
+
+    /// Create an item of the given kind exactly as described by its
+    /// archetype (i.e. without any random variance).
+    pub fn item(&self, kind: item::Kind) -> Item {
+        let archetype = self.archetype(kind);
+        Item {
+            kind,
+            glyph: archetype.glyph,
+            color: archetype.color,
+            modifier: archetype.modifier,
+            irresistible: archetype.irresistible,
+            explosion: archetype.explosion,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.archetypes.is_empty() {
+            return Err("there must be at least one item archetype".into());
+        }
+
+        let mut names = HashSet::new();
+        let mut inventory_keys = HashSet::new();
+        let mut total_weight = self.no_item_weight;
+        for archetype in &self.archetypes {
+            if archetype.name.is_empty() {
+                return Err("every item archetype must have a name".into());
+            }
+            if !names.insert(&archetype.name) {
+                return Err(format!("duplicate item archetype '{}'", archetype.name));
+            }
+            if !archetype.glyph.is_ascii() || archetype.glyph.is_whitespace() {
+                return Err(format!(
+                    "the glyph of '{}' must be a visible ASCII character, got {:?}",
+                    archetype.name,
+                    archetype.glyph
+                ));
+            }
+            if archetype.inventory_key < 1 || archetype.inventory_key > 9 {
+                return Err(format!(
+                    "the inventory key of '{}' must be between 1 and 9, got {}",
+                    archetype.name,
+                    archetype.inventory_key
+                ));
+            }
+            if !inventory_keys.insert(archetype.inventory_key) {
+                return Err(format!(
+                    "'{}' uses the inventory key {} which is already taken",
+                    archetype.name,
+                    archetype.inventory_key
+                ));
+            }
+            let explosion = archetype.explosion;
+            if explosion.initial_radius < 0 || explosion.initial_radius > explosion.radius {
+                return Err(format!(
+                    "the explosion of '{}' must have 0 <= initial_radius <= radius",
+                    archetype.name
+                ));
+            }
+            if let Some(InclusiveRange(min, max)) = archetype.mind_variance {
+                if min > max {
+                    return Err(format!(
+                        "the mind variance of '{}' must not be an empty range",
+                        archetype.name
+                    ));
+                }
+            }
+            total_weight = total_weight.checked_add(archetype.spawn_weight).ok_or(format!(
+                "the spawn weights overflow at '{}'",
+                archetype.name
+            ))?;
+        }
+
+        if total_weight == 0 {
+            return Err("the spawn weights must not all be zero".into());
+        }
+        if self.kind_by_name(&self.food).is_none() {
+            return Err(format!("the food item '{}' doesn't exist", self.food));
+        }
+        if self.kind_by_name(&self.starting_dose).is_none() {
+            return Err(format!(
+                "the starting dose '{}' doesn't exist",
+                self.starting_dose
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{ItemArchetypes, MonsterArchetypes, DEFAULT_ITEMS, DEFAULT_MONSTERS};
+    use serde_json;
+
+    fn defaults() -> MonsterArchetypes {
+        serde_json::from_str(DEFAULT_MONSTERS).unwrap()
+    }
+
+    fn default_items() -> ItemArchetypes {
+        serde_json::from_str(DEFAULT_ITEMS).unwrap()
+    }
+
+    #[test]
+    fn default_monsters_are_valid() {
+        assert_eq!(defaults().validate(), Ok(()));
+    }
+
+    #[test]
+    fn duplicate_names_are_invalid() {
+        let mut monsters = defaults();
+        let duplicate = monsters.archetypes[0].clone();
+        monsters.archetypes.push(duplicate);
+        assert!(monsters.validate().is_err());
+    }
+
+    #[test]
+    fn no_action_points_are_invalid() {
+        let mut monsters = defaults();
+        monsters.archetypes[0].max_ap = 0;
+        assert!(monsters.validate().is_err());
+    }
+
+    #[test]
+    fn non_ascii_and_blank_glyphs_are_invalid() {
+        let mut monsters = defaults();
+        monsters.archetypes[0].glyph = 'ž';
+        assert!(monsters.validate().is_err());
+        monsters.archetypes[0].glyph = ' ';
+        assert!(monsters.validate().is_err());
+    }
+
+    #[test]
+    fn zero_total_weight_is_invalid() {
+        let mut monsters = defaults();
+        monsters.no_monster_weight = 0;
+        for archetype in monsters.archetypes.iter_mut() {
+            archetype.spawn_weight = 0;
+        }
+        assert!(monsters.validate().is_err());
+    }
+
+    #[test]
+    fn default_items_are_valid() {
+        assert_eq!(default_items().validate(), Ok(()));
+    }
+
+    #[test]
+    fn duplicate_inventory_keys_are_invalid() {
+        let mut items = default_items();
+        items.archetypes[1].inventory_key = items.archetypes[0].inventory_key;
+        assert!(items.validate().is_err());
+    }
+
+    #[test]
+    fn default_inventory_keys_are_unchanged() {
+        let items = default_items();
+        let key = |name: &str| {
+            let kind = items.kind_by_name(name).expect("The item should exist.");
+            items.archetype(kind).inventory_key
+        };
+        assert_eq!(key("Food"), 1);
+        assert_eq!(key("Dose"), 2);
+        assert_eq!(key("CardinalDose"), 3);
+        assert_eq!(key("DiagonalDose"), 4);
+        assert_eq!(key("StrongDose"), 5);
+    }
+
+    #[test]
+    fn missing_food_is_invalid() {
+        let mut items = default_items();
+        items.food = "Cake".into();
+        assert!(items.validate().is_err());
+    }
+
+    #[test]
+    fn expected_count_follows_the_weights() {
+        let mut items = default_items();
+        items.no_item_weight = 0;
+        for archetype in items.archetypes.iter_mut() {
+            archetype.spawn_weight = 1;
+        }
+        let food = items.food();
+        let count = items.archetypes.len() as u32;
+        assert_eq!(items.expected_count(food, 0), 0);
+        assert_eq!(items.expected_count(food, count * 10), 10);
+        assert_eq!(items.expected_count(food, 1), 1);
+        items.archetypes[food.0].spawn_weight = 0;
+        assert_eq!(items.expected_count(food, 1000), 0);
+    }
+}
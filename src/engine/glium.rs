@@ -134,10 +134,43 @@ mod vertex {
 }
 
 
+/// Switch the window to the fullscreen mode on the monitor it's on
+/// or back to the windowed mode.
+fn set_fullscreen(
+    display: &glium::Display,
+    monitors: &[glium::glutin::MonitorId],
+    window_pos: Point,
+    fullscreen: bool,
+) {
+    if fullscreen {
+        for monitor in monitors {
+            let monitor_pos = {
+                let pos = monitor.get_position();
+                Point::new(pos.0 as i32, pos.1 as i32)
+            };
+            let monitor_dimensions = {
+                let dim = monitor.get_dimensions();
+                Point::new(dim.0 as i32, dim.1 as i32)
+            };
+
+            let monitor_bottom_left = monitor_pos + monitor_dimensions;
+            if window_pos >= monitor_pos && window_pos < monitor_bottom_left {
+                println!("Monitor: {:?}, pos: {:?}, dimensions: {:?}",
+                         monitor.get_name(), monitor.get_position(), monitor.get_dimensions());
+                display.gl_window().set_fullscreen(Some(monitor.clone()));
+            }
+        }
+    } else {
+        display.gl_window().set_fullscreen(None);
+    }
+}
+
+
 pub fn main_loop(
     display_size: Point,
     default_background: Color,
     window_title: &str,
+    mut settings: Settings,
     mut state: State,
     update: UpdateFn,
 ) {
@@ -175,10 +208,19 @@ pub fn main_loop(
 
     let texture = {
         use std::io::Cursor;
-        let data = &include_bytes!(concat!(env!("OUT_DIR"), "/font.png"))[..];
-        let image = image::load(Cursor::new(data), image::PNG)
-            .unwrap()
-            .to_rgba();
+        let image = match settings.font {
+            Some(ref font_path) => {
+                image::open(font_path)
+                    .expect(&format!("Could not load the font map at: '{}'", font_path.display()))
+                    .to_rgba()
+            }
+            None => {
+                let data = &include_bytes!(concat!(env!("OUT_DIR"), "/font.png"))[..];
+                image::load(Cursor::new(data), image::PNG)
+                    .unwrap()
+                    .to_rgba()
+            }
+        };
         let (w, h) = image.dimensions();
         assert_eq!(w % tilesize, 0);
         assert_eq!(h % tilesize, 0);
@@ -199,7 +241,6 @@ pub fn main_loop(
         }
     };
     let mut mouse = Default::default();
-    let mut settings = Settings { fullscreen: false };
     let mut drawcalls = Vec::with_capacity(4000);
     let mut lctrl_pressed = false;
     let mut rctrl_pressed = false;
@@ -217,6 +258,10 @@ pub fn main_loop(
     let mut fps = 1;
     let mut running = true;
 
+    if settings.fullscreen {
+        set_fullscreen(&display, &monitors, window_pos, true);
+    }
+
 
     while running {
         let now = Instant::now();
@@ -238,7 +283,7 @@ pub fn main_loop(
             display_size,
             default_background,
         ));
-        let previous_settings = settings;
+        let previous_settings = settings.clone();
         let update_result = update(
             &mut state,
             dt,
@@ -261,27 +306,7 @@ pub fn main_loop(
         keys.clear();
 
         if previous_settings.fullscreen != settings.fullscreen {
-            if settings.fullscreen {
-                for monitor in &monitors {
-                    let monitor_pos = {
-                        let pos = monitor.get_position();
-                        Point::new(pos.0 as i32, pos.1 as i32)
-                    };
-                    let monitor_dimensions = {
-                        let dim = monitor.get_dimensions();
-                        Point::new(dim.0 as i32, dim.1 as i32)
-                    };
-
-                    let monitor_bottom_left = monitor_pos + monitor_dimensions;
-                    if window_pos >= monitor_pos && window_pos < monitor_bottom_left {
-                        println!("Monitor: {:?}, pos: {:?}, dimensions: {:?}",
-                                 monitor.get_name(), monitor.get_position(), monitor.get_dimensions());
-                        display.gl_window().set_fullscreen(Some(monitor.clone()));
-                    }
-                }
-            } else {
-                display.gl_window().set_fullscreen(None);
-            }
+            set_fullscreen(&display, &monitors, window_pos, settings.fullscreen);
         }
 
         // Process drawcalls
@@ -648,6 +673,17 @@ pub fn main_loop(
                                 }
                             }
                         }
+                        // NOTE: `?` is on a different key on every
+                        // keyboard layout so we look at the typed
+                        // character rather than the key.
+                        WindowEvent::ReceivedCharacter('?') => {
+                            keys.push(Key {
+                                code: KeyCode::QuestionMark,
+                                alt: false,
+                                ctrl: false,
+                                shift: false,
+                            });
+                        }
                         WindowEvent::MouseMoved{ position: (x, y), ..} => {
                             let (x, y) = (x as i32, y as i32);
                             mouse.screen_pos = Point { x, y };
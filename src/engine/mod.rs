@@ -8,6 +8,8 @@ use std::borrow::Cow;
 use std::ops::RangeFrom;
 use std::time::Duration;
 
+pub use settings::Settings;
+
 
 #[cfg(feature = "opengl")]
 pub mod glium;
@@ -54,14 +56,6 @@ impl Default for Mouse {
 }
 
 
-/// Settings the engine needs to carry.
-///
-/// Things such as the fullscreen/windowed display, font size, font
-/// type, etc.
-#[derive(Debug, Clone, Copy)]
-pub struct Settings {
-    pub fullscreen: bool,
-}
 
 
 pub type UpdateFn = fn(&mut State,
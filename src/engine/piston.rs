@@ -238,7 +238,7 @@ pub fn main_loop<T>(
 
     // let mut factory = window.factory.clone();
 
-    let mut settings = Settings { fullscreen: false };
+    let mut settings = Settings::default();
     let mut drawcalls = Vec::with_capacity(8192);
     let mut lctrl_pressed = false;
     let mut rctrl_pressed = false;
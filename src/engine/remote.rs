@@ -89,7 +89,7 @@ pub fn main_loop<T>(
         Err(err) => panic!("Could not create a ZeroMQ socket: {:?}", err),
     };
 
-    let settings = Settings { fullscreen: false };
+    let settings = Settings::default();
     let mut keys = vec![];
     let mut drawcalls = Vec::with_capacity(4000);
     let mut display = Display::new(display_size.x, display_size.y);
@@ -85,6 +85,7 @@ fn key_from_tcod(tcod_key: TcodKey) -> Option<Key> {
                 'x' => Some(KeyCode::X),
                 'y' => Some(KeyCode::Y),
                 'z' => Some(KeyCode::Z),
+                '?' => Some(KeyCode::QuestionMark),
 
                 _ => None,
             }
@@ -149,7 +150,7 @@ impl Engine {
 
         Engine {
             root: root,
-            settings: Settings { fullscreen: false },
+            settings: Settings::default(),
         }
     }
 
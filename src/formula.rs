@@ -1,113 +1,260 @@
-use item::{Kind, Item};
+use data;
+use item::Item;
 use player::{Bonus, CauseOfDeath, Mind, Modifier, Player};
 use ranged_int::{InclusiveRange, Ranged};
+use serde_json::{self, Map, Value};
 use std::cmp;
 
 
-pub const INITIAL_SAFE_RADIUS: i32 = 25;
-pub const INITIAL_EASY_RADIUS: i32 = 40;
-pub const NO_LETHAL_DOSE_RADIUS: i32 = 6;
-
-pub const ANXIETIES_PER_WILL: InclusiveRange = InclusiveRange(0, 7);
-
-pub const WILL: InclusiveRange = InclusiveRange(0, 5);
-
-// The rate at which the Mind drops. This is higher than one so we can
-// apply bonuses that slow the rate down.
-pub const MIND_DROP_PER_TURN: i32 = 2;
-
-// NOTE: We use the MIND_DROP_PER_TURN multiple here. That way, unless
-// it's modified, the number here contains the default pace in turns.
-pub const WITHDRAWAL: InclusiveRange = InclusiveRange(0, 15 * MIND_DROP_PER_TURN);
-pub const SOBER: InclusiveRange = InclusiveRange(0, 20 * MIND_DROP_PER_TURN);
-pub const HIGH: InclusiveRange = InclusiveRange(0, 80 * MIND_DROP_PER_TURN);
-
-pub const DOSE_PREFAB: Item = Item {
-    kind: Kind::Dose,
-    irresistible: 2,
-    modifier: Modifier::Intoxication {
-        state_of_mind: 70 * MIND_DROP_PER_TURN,
-        tolerance_increase: 1,
-    },
-};
-
-pub const STRONG_DOSE_PREFAB: Item = Item {
-    kind: Kind::StrongDose,
-    irresistible: 4,
-    modifier: Modifier::Intoxication {
-        state_of_mind: 130 * MIND_DROP_PER_TURN,
-        tolerance_increase: 3,
-    },
-};
-
-pub const CARDINAL_DOSE_PREFAB: Item = Item {
-    kind: Kind::CardinalDose,
-    irresistible: 3,
-    modifier: Modifier::Intoxication {
-        state_of_mind: 95 * MIND_DROP_PER_TURN,
-        tolerance_increase: 2,
-    },
-};
-
-pub const DIAGONAL_DOSE_PREFAB: Item = Item {
-    kind: Kind::DiagonalDose,
-    irresistible: 3,
-    modifier: Modifier::Intoxication {
-        state_of_mind: 95 * MIND_DROP_PER_TURN,
-        tolerance_increase: 2,
-    },
-};
-
-pub const FOOD_PREFAB: Item = Item {
-    kind: Kind::Food,
-    irresistible: 0,
-    modifier: Modifier::Attribute {
-        state_of_mind: 10 * MIND_DROP_PER_TURN,
-        will: 0,
-    },
-};
-
-// This how much a given dose can vary from the prefab's base value
-pub const DOSE_MIND_VARIANCE: InclusiveRange = InclusiveRange(-5, 5);
-pub const STRONG_DOSE_MIND_VARIANCE: InclusiveRange = InclusiveRange(-15, -15);
-pub const CARDINAL_DOSE_MIND_VARIANCE: InclusiveRange = InclusiveRange(-10, 10);
-pub const DIAGONAL_DOSE_MIND_VARIANCE: InclusiveRange = InclusiveRange(-10, 10);
-
-pub const PLAYER_BASE_AP: i32 = 1;
-pub const SOBRIETY_COUNTER: InclusiveRange = InclusiveRange(0, 100);
-pub const PANIC_TURNS: InclusiveRange = InclusiveRange(0, 10);
-pub const STUN_TURNS: InclusiveRange = InclusiveRange(0, 10);
-
-pub const CHASING_DISTANCE: i32 = 5;
-pub const HOWLING_DISTANCE: i32 = 15;
-
-pub const ESTRANGED_NPC_MAX_AP: i32 = 2;
+/// The tuning values of the game.
+///
+/// The defaults are what the game ships with. Any of them can be
+/// overridden in `data/balance.json`, the rest keeps the default
+/// value. The balance a game was played with is stored in its replay.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Balance {
+    pub initial_safe_radius: i32,
+    pub initial_easy_radius: i32,
+    pub no_lethal_dose_radius: i32,
+
+    pub anxieties_per_will: InclusiveRange,
+    pub will: InclusiveRange,
+
+    /// The rate at which the Mind drops. This is higher than one so
+    /// we can apply bonuses that slow the rate down.
+    pub mind_drop_per_turn: i32,
+
+    pub withdrawal: InclusiveRange,
+    pub sober: InclusiveRange,
+    pub high: InclusiveRange,
+
+    pub player_base_ap: i32,
+    pub sobriety_counter: InclusiveRange,
+    pub panic_turns: InclusiveRange,
+    pub stun_turns: InclusiveRange,
+    pub medication_turns: InclusiveRange,
+    pub dose_blocker_turns: InclusiveRange,
+    /// The player earns a sobriety token every this many points of
+    /// the `sobriety_counter`.
+    pub sobriety_token_period: i32,
+
+    /// A dose that leaves the player this close to the maximum of
+    /// the High range is shown as risky (see `overdose_risk`).
+    pub overdose_warning_margin: i32,
+
+    /// The player loses a point of tolerance every this many turns
+    /// they spend without being High.
+    pub tolerance_decay_period: i32,
+    /// How much longer and harsher the withdrawal gets with every
+    /// dose the player has ever used, in percent.
+    pub withdrawal_growth_per_dose: i32,
+    /// The withdrawal never grows by more than this many percent.
+    pub max_withdrawal_growth: i32,
+
+    pub chasing_distance: i32,
+    pub howling_distance: i32,
+
+    pub estranged_npc_max_ap: i32,
+
+    /// The world is fully simulated within this many tiles from the
+    /// player and kept loaded within twice that. It doesn't depend on
+    /// the `map_size` setting so a replay plays out the same no
+    /// matter how big the window is.
+    pub simulation_radius: i32,
+    /// The loaded chunks outside of the simulation area are only
+    /// processed once every this many turns.
+    pub coarse_simulation_period: i32,
+    /// Percent chance a chunk outside of the simulation area gets a
+    /// new piece of food on each coarse simulation step.
+    pub offscreen_food_respawn_chance: u32,
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        Balance::with_mind_drop_per_turn(2)
+    }
+}
+
+impl Balance {
+    /// The default balance for the given pace of the Mind.
+    fn with_mind_drop_per_turn(mind_drop_per_turn: i32) -> Self {
+        Balance {
+            initial_safe_radius: 25,
+            initial_easy_radius: 40,
+            no_lethal_dose_radius: 6,
+
+            anxieties_per_will: InclusiveRange(0, 7),
+            will: InclusiveRange(0, 5),
+
+            mind_drop_per_turn,
+
+            // NOTE: We use the `mind_drop_per_turn` multiple here.
+            // That way, unless it's modified, the number here
+            // contains the default pace in turns.
+            withdrawal: InclusiveRange(0, 15 * mind_drop_per_turn),
+            sober: InclusiveRange(0, 20 * mind_drop_per_turn),
+            high: InclusiveRange(0, 80 * mind_drop_per_turn),
+
+            player_base_ap: 1,
+            sobriety_counter: InclusiveRange(0, 100),
+            panic_turns: InclusiveRange(0, 10),
+            stun_turns: InclusiveRange(0, 10),
+            medication_turns: InclusiveRange(0, 60),
+            dose_blocker_turns: InclusiveRange(0, 60),
+            sobriety_token_period: 25,
+
+            overdose_warning_margin: 10 * mind_drop_per_turn,
+
+            tolerance_decay_period: 10,
+            withdrawal_growth_per_dose: 5,
+            max_withdrawal_growth: 100,
+
+            chasing_distance: 5,
+            howling_distance: 15,
+
+            estranged_npc_max_ap: 2,
+
+            simulation_radius: 43,
+            coarse_simulation_period: 4,
+            offscreen_food_respawn_chance: 5,
+        }
+    }
+
+    /// Load the balance overrides from `data/balance.json`. If the
+    /// file doesn't exist, the defaults are used.
+    pub fn load() -> Self {
+        let overrides: Map<String, Value> = data::load("balance.json", "{}", |overrides| {
+            Balance::from_overrides(overrides).and_then(|balance| balance.validate())
+        });
+        Balance::from_overrides(&overrides).expect("The balance overrides were validated.")
+    }
 
+    /// The defaults with the `overrides` on top.
+    ///
+    /// The Mind ranges that aren't overridden follow the
+    /// `mind_drop_per_turn` so they keep their length in turns when
+    /// only the pace changes.
+    pub fn from_overrides(overrides: &Map<String, Value>) -> Result<Self, String> {
+        let mind_drop_per_turn = match overrides.get("mind_drop_per_turn") {
+            Some(value) => match value.as_i64() {
+                Some(value) => value as i32,
+                None => return Err("mind_drop_per_turn must be a whole number".into()),
+            },
+            None => Balance::default().mind_drop_per_turn,
+        };
+        let defaults = Balance::with_mind_drop_per_turn(mind_drop_per_turn);
+        let mut values = match serde_json::to_value(defaults) {
+            Ok(Value::Object(values)) => values,
+            _ => unreachable!(),
+        };
+        for (name, value) in overrides {
+            values.insert(name.clone(), value.clone());
+        }
+        serde_json::from_value(Value::Object(values)).map_err(|err| err.to_string())
+    }
 
+    pub fn validate(&self) -> Result<(), String> {
+        if self.initial_safe_radius < 0 || self.no_lethal_dose_radius < 0 {
+            return Err("the starting area radii must not be negative".into());
+        }
+        if self.initial_safe_radius > self.initial_easy_radius {
+            return Err("initial_safe_radius must not be larger than initial_easy_radius".into());
+        }
+
+        let ranges = [
+            ("anxieties_per_will", self.anxieties_per_will),
+            ("will", self.will),
+            ("withdrawal", self.withdrawal),
+            ("sober", self.sober),
+            ("high", self.high),
+            ("sobriety_counter", self.sobriety_counter),
+            ("panic_turns", self.panic_turns),
+            ("stun_turns", self.stun_turns),
+            ("medication_turns", self.medication_turns),
+            ("dose_blocker_turns", self.dose_blocker_turns),
+        ];
+        for &(name, InclusiveRange(min, max)) in ranges.iter() {
+            // NOTE: `Ranged::percent` divides by the size of the range
+            if min >= max {
+                return Err(format!("{} must have its minimum lower than its maximum", name));
+            }
+        }
+
+        if self.mind_drop_per_turn < 0 {
+            return Err("mind_drop_per_turn must not be negative".into());
+        }
+        if self.overdose_warning_margin < 1 {
+            return Err("overdose_warning_margin must be at least 1".into());
+        }
+        if self.tolerance_decay_period < 1 {
+            return Err("tolerance_decay_period must be at least 1".into());
+        }
+        if self.withdrawal_growth_per_dose < 0 || self.max_withdrawal_growth < 0 {
+            return Err("the withdrawal must not get shorter with use".into());
+        }
+        if self.sobriety_token_period < 1 {
+            return Err("sobriety_token_period must be at least 1".into());
+        }
+        if self.player_base_ap < 1 || self.estranged_npc_max_ap < 1 {
+            return Err("everyone must have at least 1 action point".into());
+        }
+        if self.chasing_distance < 0 || self.howling_distance < 0 {
+            return Err("the monster distances must not be negative".into());
+        }
+        if self.simulation_radius < 1 {
+            return Err("simulation_radius must be at least 1".into());
+        }
+        if self.coarse_simulation_period < 1 {
+            return Err("coarse_simulation_period must be at least 1".into());
+        }
+        if self.offscreen_food_respawn_chance > 100 {
+            return Err("offscreen_food_respawn_chance is a percentage".into());
+        }
+
+        Ok(())
+    }
+}
+
+
+/// The smallest `exploration_radius` there is.
+const MIN_EXPLORATION_RADIUS: i32 = 4;
 
 pub fn exploration_radius(mental_state: Mind) -> i32 {
     use player::Mind::*;
     match mental_state {
-        Withdrawal(value) => if *value >= value.middle() { 5 } else { 4 },
+        Withdrawal(value) => if *value >= value.middle() { 5 } else { MIN_EXPLORATION_RADIUS },
         Sober(_) => 6,
         High(value) => if *value >= value.middle() { 8 } else { 7 },
     }
 }
 
 
+/// The biggest `map_size` setting whose view stays within the
+/// simulation area. The view is only re-centred once the player gets
+/// within the exploration radius of its edge, so its far edge can be
+/// `map_size - 1 - MIN_EXPLORATION_RADIUS` tiles away from them.
+/// Anything past the simulation area is never generated.
+pub fn max_map_size(balance: &Balance) -> i32 {
+    balance.simulation_radius + MIN_EXPLORATION_RADIUS + 1
+}
+
+
 pub fn player_resist_radius(dose_irresistible_value: i32, will: i32) -> i32 {
     cmp::max(dose_irresistible_value + 2 - will, 0)
 }
 
 
-pub fn mind_take_turn(mind: Mind, drop: i32) -> Mind {
+/// `doses_used` is the number of doses the player has ever used. The
+/// withdrawal gets longer with it (see `withdrawal_range`).
+pub fn mind_take_turn(mind: Mind, drop: i32, doses_used: i32, balance: &Balance) -> Mind {
     use self::Mind::*;
     match mind {
         Withdrawal(value) => Withdrawal(value - drop),
         Sober(value) => {
             let new_value = value - drop;
             if new_value.is_min() {
-                Withdrawal(Ranged::new_max(WITHDRAWAL))
+                Withdrawal(Ranged::new_max(withdrawal_range(doses_used, balance)))
             } else {
                 Sober(new_value)
             }
@@ -115,7 +262,7 @@ pub fn mind_take_turn(mind: Mind, drop: i32) -> Mind {
         High(value) => {
             let new_value = value - drop;
             if new_value.is_min() {
-                Withdrawal(Ranged::new_max(WITHDRAWAL))
+                Withdrawal(Ranged::new_max(withdrawal_range(doses_used, balance)))
             } else {
                 High(new_value)
             }
@@ -126,11 +273,11 @@ pub fn mind_take_turn(mind: Mind, drop: i32) -> Mind {
 
 /// Update the `Mind` when eating food or being hit by the Hunger
 /// monster.
-pub fn process_hunger(mind: Mind, amount: i32) -> Mind {
+pub fn process_hunger(mind: Mind, amount: i32, doses_used: i32, balance: &Balance) -> Mind {
     match mind {
         Mind::Withdrawal(val) => {
             if (*val + amount) > val.max() {
-                let new_val = Ranged::new_min(SOBER);
+                let new_val = Ranged::new_min(balance.sober);
                 Mind::Sober(new_val + (val.max() - *val + amount))
             } else {
                 Mind::Withdrawal(val + amount)
@@ -141,7 +288,7 @@ pub fn process_hunger(mind: Mind, amount: i32) -> Mind {
             if (*val + amount) >= val.min() {
                 Mind::Sober(val + amount)
             } else {
-                let new_val = Ranged::new_max(WITHDRAWAL);
+                let new_val = Ranged::new_max(withdrawal_range(doses_used, balance));
                 let amount = val.min() - *val + amount;
                 Mind::Withdrawal(new_val + amount)
             }
@@ -161,20 +308,102 @@ pub fn process_hunger(mind: Mind, amount: i32) -> Mind {
 }
 
 
-pub fn intoxicate(mind: Mind, tolerance: i32, expected_increment: i32) -> Mind {
-    let increment = cmp::max(10, expected_increment - tolerance);
+/// How much worse the withdrawal is for someone who has used
+/// `doses_used` doses so far, in percent of the base withdrawal.
+pub fn withdrawal_severity(doses_used: i32, balance: &Balance) -> i32 {
+    let growth = doses_used * balance.withdrawal_growth_per_dose;
+    100 + cmp::min(growth, balance.max_withdrawal_growth)
+}
+
+
+/// The range of the withdrawal the player goes into. The more doses
+/// they've used, the longer it is: the same food gets them less far
+/// out of it and the Mind still drops by the same amount each turn so
+/// it takes longer to get through.
+pub fn withdrawal_range(doses_used: i32, balance: &Balance) -> InclusiveRange {
+    let InclusiveRange(min, max) = balance.withdrawal;
+    let severity = withdrawal_severity(doses_used, balance);
+    InclusiveRange(min, min + (max - min) * severity / 100)
+}
+
+
+/// The tolerance after another turn the player spent without being
+/// High. `clean_turns` counts those turns including this one.
+///
+/// It fades slowly, but it does fade. So a player who relapses after
+/// a long time clean gets hit by the dose much harder than they're
+/// used to and can overdose on a dose that used to be safe for them.
+pub fn decay_tolerance(tolerance: i32, clean_turns: i32, balance: &Balance) -> i32 {
+    if clean_turns > 0 && clean_turns % balance.tolerance_decay_period == 0 {
+        cmp::max(0, tolerance - 1)
+    } else {
+        tolerance
+    }
+}
+
+
+/// How much a dose of the given strength actually gets the player
+/// high. Tolerance weakens it, but only up to a point.
+pub fn effective_dose_strength(dose_strength: i32, tolerance: i32) -> i32 {
+    cmp::max(10, dose_strength - tolerance)
+}
+
+
+pub fn intoxicate(mind: Mind, tolerance: i32, expected_increment: i32, balance: &Balance) -> Mind {
+    let increment = effective_dose_strength(expected_increment, tolerance);
 
     // If we're high, the increment adds to the current intoxication
     // value, otherwise we go to high directly, ignoring any
     // withdrawn/sober states.
     match mind {
         Mind::Withdrawal(_) |
-        Mind::Sober(_) => Mind::High(Ranged::new(increment, HIGH)),
+        Mind::Sober(_) => Mind::High(Ranged::new(increment, balance.high)),
         Mind::High(val) => Mind::High(val + increment),
     }
 }
 
 
+/// The risk of overdosing when using the `dose` right now. It goes
+/// from 0.0 (safe) to 1.0 (certain death).
+///
+/// The dose's strength is known exactly so it either kills the player
+/// or it doesn't. But a dose that leaves them just short of the
+/// maximum is dangerous too: the next one they come across (or get
+/// pulled into) will finish them off. So the risk grows as the
+/// remaining headroom gets within `overdose_warning_margin`.
+pub fn overdose_risk(mind: Mind, tolerance: i32, dose: &Item, balance: &Balance) -> f32 {
+    let dose_strength = match dose.modifier {
+        Modifier::Intoxication { state_of_mind, .. } => state_of_mind,
+        _ => return 0.0,
+    };
+    match intoxicate(mind, tolerance, dose_strength, balance) {
+        Mind::High(value) => {
+            let headroom = value.max() - *value;
+            let margin = balance.overdose_warning_margin;
+            if headroom >= margin {
+                0.0
+            } else {
+                1.0 - (headroom as f32 / margin as f32)
+            }
+        }
+        Mind::Withdrawal(_) | Mind::Sober(_) => 0.0,
+    }
+}
+
+
+/// Whether the player earns a sobriety token for getting the
+/// `sobriety_counter` this far. The last milestone doesn't count:
+/// that one's the victory.
+///
+/// Every milestone is only awarded once: the counter is reset after
+/// a relapse so the player has to get past the furthest one they
+/// reached (i.e. the `tokens` they hold) to earn another token.
+pub fn sobriety_token_earned(sobriety_counter: Ranged, tokens: i32, balance: &Balance) -> bool {
+    let milestone = *sobriety_counter / balance.sobriety_token_period;
+    milestone > tokens && !sobriety_counter.is_max()
+}
+
+
 pub fn mind_bonus(mind: Mind) -> Option<Bonus> {
     match mind {
         Mind::High(val) if *val == val.max() - 1 => Some(Bonus::UncoverMap),
@@ -210,3 +439,215 @@ pub fn mind_fade_value(mind: Mind) -> f32 {
         Sober(_) | High(_) => 0.0,
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::{decay_tolerance, mind_take_turn, overdose_risk, sobriety_token_earned,
+                withdrawal_range, Balance};
+    use item::{Explosion, ExplosionShape, Item, Kind};
+    use color;
+    use player::{Mind, Modifier};
+    use ranged_int::{InclusiveRange, Ranged};
+    use serde_json;
+
+    fn dose(state_of_mind: i32) -> Item {
+        Item {
+            kind: Kind(0),
+            glyph: 'i',
+            color: color::gui_text,
+            modifier: Modifier::Intoxication { state_of_mind, tolerance_increase: 1 },
+            irresistible: 2,
+            explosion: Explosion { shape: ExplosionShape::Square, radius: 6, initial_radius: 2 },
+        }
+    }
+
+    #[test]
+    fn weak_dose_while_sober_is_safe() {
+        let balance = Balance::default();
+        let mind = Mind::Sober(Ranged::new_max(balance.sober));
+        assert_eq!(overdose_risk(mind, 0, &dose(100), &balance), 0.0);
+    }
+
+    #[test]
+    fn dose_over_the_maximum_is_certain_death() {
+        let balance = Balance::default();
+        let mind = Mind::Sober(Ranged::new_max(balance.sober));
+        let strength = balance.high.1 + 10;
+        assert_eq!(overdose_risk(mind, 0, &dose(strength), &balance), 1.0);
+    }
+
+    #[test]
+    fn second_dose_while_high_adds_up() {
+        let balance = Balance::default();
+        let mind = Mind::High(Ranged::new(100, balance.high));
+        assert_eq!(overdose_risk(mind, 0, &dose(100), &balance), 1.0);
+    }
+
+    #[test]
+    fn risk_grows_close_to_the_maximum() {
+        let balance = Balance::default();
+        let mind = Mind::Sober(Ranged::new_max(balance.sober));
+        let close = balance.high.1 - balance.overdose_warning_margin / 2;
+        let risk = overdose_risk(mind, 0, &dose(close), &balance);
+        assert!(risk > 0.0 && risk < 1.0);
+    }
+
+    #[test]
+    fn tolerance_lowers_the_risk() {
+        let balance = Balance::default();
+        let mind = Mind::Sober(Ranged::new_max(balance.sober));
+        let strength = balance.high.1;
+        assert_eq!(overdose_risk(mind, 0, &dose(strength), &balance), 1.0);
+        assert_eq!(overdose_risk(mind, 100, &dose(strength), &balance), 0.0);
+    }
+
+    #[test]
+    fn food_is_never_risky() {
+        let balance = Balance::default();
+        let mind = Mind::High(Ranged::new(150, balance.high));
+        let mut food = dose(0);
+        food.modifier = Modifier::Attribute { will: 0, state_of_mind: 20 };
+        assert_eq!(overdose_risk(mind, 0, &food, &balance), 0.0);
+    }
+
+    #[test]
+    fn tolerance_decays_slowly() {
+        let balance = Balance::default();
+        let period = balance.tolerance_decay_period;
+        assert_eq!(decay_tolerance(10, 1, &balance), 10);
+        assert_eq!(decay_tolerance(10, period - 1, &balance), 10);
+        assert_eq!(decay_tolerance(10, period, &balance), 9);
+        assert_eq!(decay_tolerance(10, 2 * period, &balance), 9);
+    }
+
+    #[test]
+    fn tolerance_never_goes_negative() {
+        let balance = Balance::default();
+        let period = balance.tolerance_decay_period;
+        assert_eq!(decay_tolerance(0, period, &balance), 0);
+    }
+
+    #[test]
+    fn first_withdrawal_has_the_base_length() {
+        let balance = Balance::default();
+        assert_eq!(withdrawal_range(0, &balance), balance.withdrawal);
+    }
+
+    #[test]
+    fn withdrawal_grows_with_use() {
+        let balance = Balance::default();
+        let InclusiveRange(_, few) = withdrawal_range(2, &balance);
+        let InclusiveRange(_, many) = withdrawal_range(10, &balance);
+        assert!(balance.withdrawal.1 < few && few < many);
+    }
+
+    /// How many turns it takes to get from the top of the withdrawal
+    /// to exhaustion.
+    fn withdrawal_turns(doses_used: i32, balance: &Balance) -> i32 {
+        let mut mind = Mind::Withdrawal(Ranged::new_max(withdrawal_range(doses_used, balance)));
+        let mut turns = 0;
+        loop {
+            match mind {
+                Mind::Withdrawal(value) if value.is_min() => return turns,
+                _ => {}
+            }
+            mind = mind_take_turn(mind, balance.mind_drop_per_turn, doses_used, balance);
+            turns += 1;
+        }
+    }
+
+    #[test]
+    fn withdrawal_lasts_longer_with_use() {
+        let balance = Balance::default();
+        assert_eq!(withdrawal_turns(0, &balance), 15);
+        assert!(withdrawal_turns(2, &balance) > withdrawal_turns(0, &balance));
+        assert!(withdrawal_turns(10, &balance) > withdrawal_turns(2, &balance));
+        assert_eq!(withdrawal_turns(1000, &balance), 30);
+    }
+
+    #[test]
+    fn withdrawal_growth_is_capped() {
+        let balance = Balance::default();
+        assert_eq!(withdrawal_range(1000, &balance), withdrawal_range(10000, &balance));
+        assert_eq!(withdrawal_range(1000, &balance).1, balance.withdrawal.1 * 2);
+    }
+
+    #[test]
+    fn sobering_up_goes_into_the_longer_withdrawal() {
+        let balance = Balance::default();
+        let mind = Mind::Sober(Ranged::new(1, balance.sober));
+        match mind_take_turn(mind, 1, 10, &balance) {
+            Mind::Withdrawal(value) => assert_eq!(value.max(), withdrawal_range(10, &balance).1),
+            mind => panic!("Expected withdrawal, got: {:?}", mind),
+        }
+    }
+
+    #[test]
+    fn relapse_after_long_sobriety_is_dangerous() {
+        let balance = Balance::default();
+        let mind = Mind::Sober(Ranged::new_max(balance.sober));
+        let strength = balance.high.1;
+        let mut tolerance = 100;
+        assert_eq!(overdose_risk(mind, tolerance, &dose(strength), &balance), 0.0);
+        for clean_turns in 1..(1000 * balance.tolerance_decay_period) {
+            tolerance = decay_tolerance(tolerance, clean_turns, &balance);
+        }
+        assert_eq!(tolerance, 0);
+        assert_eq!(overdose_risk(mind, tolerance, &dose(strength), &balance), 1.0);
+    }
+
+    #[test]
+    fn sobriety_tokens_come_at_milestones() {
+        let balance = Balance::default();
+        let period = balance.sobriety_token_period;
+        let counter = |value| Ranged::new(value, balance.sobriety_counter);
+        assert!(!sobriety_token_earned(counter(0), 0, &balance));
+        assert!(!sobriety_token_earned(counter(period - 1), 0, &balance));
+        assert!(sobriety_token_earned(counter(period), 0, &balance));
+        assert!(!sobriety_token_earned(counter(period + 1), 1, &balance));
+        assert!(sobriety_token_earned(counter(2 * period), 1, &balance));
+        assert!(!sobriety_token_earned(counter(balance.sobriety_counter.1), 0, &balance));
+    }
+
+    #[test]
+    fn relapse_doesnt_earn_the_tokens_again() {
+        let balance = Balance::default();
+        let period = balance.sobriety_token_period;
+        let mut tokens = 0;
+        for _relapse in 0..5 {
+            for value in 0..(period + 1) {
+                let counter = Ranged::new(value, balance.sobriety_counter);
+                if sobriety_token_earned(counter, tokens, &balance) {
+                    tokens += 1;
+                }
+            }
+        }
+        assert_eq!(tokens, 1);
+    }
+
+    #[test]
+    fn no_overrides_means_the_defaults() {
+        let overrides = serde_json::from_str("{}").unwrap();
+        assert_eq!(Balance::from_overrides(&overrides), Ok(Balance::default()));
+    }
+
+    #[test]
+    fn mind_ranges_follow_the_mind_drop() {
+        let overrides = serde_json::from_str(r#"{"mind_drop_per_turn": 3}"#).unwrap();
+        let balance = Balance::from_overrides(&overrides).unwrap();
+        assert_eq!(balance.mind_drop_per_turn, 3);
+        assert_eq!(balance.withdrawal, InclusiveRange(0, 15 * 3));
+        assert_eq!(balance.sober, InclusiveRange(0, 20 * 3));
+        assert_eq!(balance.high, InclusiveRange(0, 80 * 3));
+    }
+
+    #[test]
+    fn explicit_ranges_win_over_the_mind_drop() {
+        let overrides =
+            serde_json::from_str(r#"{"mind_drop_per_turn": 3, "sober": [0, 10]}"#).unwrap();
+        let balance = Balance::from_overrides(&overrides).unwrap();
+        assert_eq!(balance.sober, InclusiveRange(0, 10));
+        assert_eq!(balance.withdrawal, InclusiveRange(0, 15 * 3));
+    }
+}
@@ -1,28 +1,36 @@
 use animation::{self, AreaOfEffect};
-use ai::{PlayerInfo};
+use ai::{self, AIState, PlayerInfo};
 use blocker::Blocker;
 use color;
+use data::ItemArchetypes;
 use engine::{Draw, Mouse, Settings};
-use formula;
-use item;
+use formula::{self, Balance};
+use help::{self, HelpScreen};
+use item::{self, Equipment};
+use keymap::{self, GameAction, KeyBindings, MetaAction};
 use keys::{Key, KeyCode, Keys};
-use level::TileKind;
-use monster::{self, CompanionBonus};
+use level::{Tile, TileKind};
+use messages::{Event, MessageLog};
+use monster::{self, CompanionBonus, MonsterId};
 use pathfinding;
 use player;
-use point::Point;
+use point::{CircularArea, Point};
 
-use rand::Rng;
+use rand::{IsaacRng, Rng, SeedableRng};
 use rect::Rectangle;
 use render;
-use state::{self, Command, Side, State};
+use state::{self, Command, Rest, Side, State, Travel, TravelGoal};
 use stats::{FrameStats, Stats};
+use status::{self, ActionFilter};
+use std::cmp;
 use std::collections::{HashMap, VecDeque};
 use std::u64;
 use std::io::Write;
 use std::iter::FromIterator;
 use std::time::Duration;
 use timer::{Stopwatch, Timer};
+use tips;
+use tutorial;
 use util;
 use world::World;
 
@@ -32,6 +40,7 @@ pub enum Action {
     Move(Point),
     Attack(Point, player::Modifier),
     Use(item::Kind),
+    Wait,
 }
 
 
@@ -56,11 +65,45 @@ pub fn update(
     state.clock = state.clock + dt;
     state.replay_step = state.replay_step + dt;
 
+    let left_clicked = mouse.left && !state.mouse.left;
     state.keys.extend(new_keys.iter().cloned());
     state.mouse = mouse;
 
+    // NOTE: any key press takes the control back from the travel
+    // or rest
+    if !new_keys.is_empty() {
+        state.travel = None;
+        state.rest = None;
+    }
+
+    // Travel to the clicked tile
+    if left_clicked && state.player_in_control() {
+        if let Some(destination) = state.mouse_travel_destination() {
+            start_travel(state, TravelGoal::Destination(destination));
+        }
+    }
+
+    if state.key_bindings_screen.is_some() {
+        process_key_bindings_screen(state, settings);
+    }
+
+    if state.help_screen.is_some() {
+        process_help_screen(state, settings);
+    }
+
+    if state.message_log_screen.is_some() {
+        process_message_log_screen(state, settings);
+    }
+
+    if state.look_cursor.is_some() {
+        process_look_mode(state, settings);
+    }
+
+    let replay = state.replay;
+
     // Quit the game when Q is pressed or on replay and requested
-    if state.keys.matches_code(KeyCode::Q) || (!state.player.alive() && state.exit_after) ||
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::Quit) ||
+        (!state.player.alive() && state.exit_after) ||
         (state.replay && state.exit_after &&
              (state.commands.is_empty() ||
                   (!state.player.alive() && state.screen_fading.is_none())))
@@ -70,48 +113,88 @@ pub fn update(
     }
 
     // Restart the game on F5
-    if state.keys.matches_code(KeyCode::F5) || state.endgame_screen_visible && state.keys.matches_code(KeyCode::N) {
-        let state = State::new_game(
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::NewGame) {
+        let replay_path = if settings.record_replays {
+            state::generate_replay_path()
+        } else {
+            None
+        };
+        let mut new_state = State::new_game(
             state.world_size,
             state.map_size.x,
             state.panel_width,
             state.display_size,
             state.exit_after,
-            state::generate_replay_path(),
+            replay_path,
             state.player.invincible,
         );
-        return RunningState::NewGame(state);
+        new_state.shown_tips = state.shown_tips.clone();
+        return RunningState::NewGame(new_state);
     }
 
     // Full screen on Alt-Enter
-    if state.keys.matches(|k| k.alt && k.code == KeyCode::Enter) {
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::ToggleFullscreen) {
         settings.fullscreen = !settings.fullscreen;
+        settings.save();
+    }
+
+    // Cycle through the colour themes on F7
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::CycleColorTheme) {
+        settings.color_theme = settings.color_theme.next();
+        settings.save();
+    }
+
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::KeyBindings) {
+        state.key_bindings_screen = Some(Default::default());
+    }
+
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::Help) {
+        state.help_screen = Some(Default::default());
+    }
+
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::MessageLog) {
+        state.message_log_screen = Some(message_log_max_scroll(state));
+    }
+
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::Look) {
+        state.look_cursor = Some(state.player.pos);
     }
 
     // Uncover map / set the Cheat mode
-    if state.keys.matches_code(KeyCode::F6) {
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::ToggleCheats) {
         state.cheating = !state.cheating;
     }
 
     // NOTE: this will not show up in the replay so that'll be out of
     // sync. We can pass `--invincible` while running the replay
     // though and that should always work, I think.
-    if state.keys.matches_code(KeyCode::I) && state.cheating {
-        println!("Making the player invincible!");
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::CheatInvincible) &&
+        state.cheating
+    {
         state.player.invincible = true;
+        state.messages.add(Event::Invincible);
     }
 
-    if state.keys.matches_code(KeyCode::F) && state.cheating {
-        state.player.inventory.push(formula::FOOD_PREFAB);
+    if meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::CheatFood) &&
+        state.cheating
+    {
+        let food = {
+            let item_archetypes = state.world.item_archetypes();
+            item_archetypes.item(item_archetypes.food())
+        };
+        state.player.inventory.push(food);
     }
 
-    state.paused = if state.replay && state.keys.matches_code(KeyCode::Space) {
+    state.paused = if replay &&
+        meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::PauseReplay)
+    {
         !state.paused
     } else {
         state.paused
     };
 
-    let paused_one_step = state.paused && state.keys.matches_code(KeyCode::Right);
+    let paused_one_step = state.paused &&
+        meta_action_pressed(&mut state.keys, &settings.key_bindings, replay, MetaAction::StepReplay);
     let timed_step = if state.replay && !state.paused &&
         (util::num_milliseconds(state.replay_step) >= 50 || state.replay_full_speed)
     {
@@ -136,25 +219,54 @@ pub fn update(
     let running = !state.paused && !state.replay;
     let mut spent_turn = false;
     let no_animations = state.explosion_animation.is_none() && state.pos_timer.finished();
-    let simulation_area = Rectangle::center(state.player.pos, state.map_size);
+    let simulation_area = state.simulation_area();
 
     if (running || paused_one_step || timed_step) && state.side != Side::Victory && no_animations {
-        process_keys(&mut state.keys, &mut state.commands);
+        let auto_action = process_keys(
+            &mut state.keys,
+            &mut state.commands,
+            &settings.key_bindings,
+            replay,
+            state.world.item_archetypes(),
+        );
+        if let Some(action) = auto_action {
+            if !state.player_in_control() {
+                // NOTE: e.g. a replay is running
+            } else if action == GameAction::Rest {
+                start_rest(state, settings.rest_turns);
+            } else {
+                match travel_goal(action, &state.player, &state.world) {
+                    Some(goal) => start_travel(state, goal),
+                    None => state.messages.add(Event::NothingInSight(action)),
+                }
+            }
+        }
+
+        if state.commands.is_empty() {
+            process_rest(state);
+        }
+        if state.commands.is_empty() {
+            process_travel(state);
+        }
 
         let command_count = state.commands.len();
 
         // NOTE: Process player
-        process_player(state, simulation_area);
+        process_player(state);
 
         // NOTE: Process monsters
         if state.player.ap() <= 0 && state.explosion_animation.is_none() {
+            let loaded_area = state.loaded_area();
             process_monsters(
                 &mut state.world,
                 &mut state.player,
                 simulation_area,
+                loaded_area,
                 &mut state.rng,
+                &mut state.messages,
+                &state.balance,
             );
-            state.player.new_turn();
+            state.player.new_turn(&state.balance);
         }
 
         spent_turn = command_count > state.commands.len();
@@ -162,13 +274,31 @@ pub fn update(
 
     if spent_turn {
         state.turn += 1;
+        state.messages.new_turn(state.turn);
     }
 
-    // NOTE: Load up new chunks if necessary
+    // NOTE: Load up new chunks if necessary and store the ones far
+    // away on the disk
     if spent_turn {
         for pos in simulation_area.points() {
             state.world.ensure_chunk_at_pos(pos);
         }
+        let loaded_area = state.loaded_area();
+        if state.turn % state.balance.coarse_simulation_period == 0 {
+            process_offscreen_world(
+                &mut state.world,
+                &state.player,
+                simulation_area,
+                loaded_area,
+                state.chunk_size,
+                state.seed,
+                state.turn,
+                &state.balance,
+            );
+        }
+        if state.world.unload_chunks_outside(loaded_area) {
+            state.messages.add(Event::ChunkCacheFailed);
+        }
     }
 
     // Run the dose explosion effect here:
@@ -186,6 +316,8 @@ pub fn update(
         }
     }
 
+    tutorial::update(state);
+
     // Log or check verifications
     if spent_turn {
         if state.replay {
@@ -219,6 +351,13 @@ pub fn update(
 
     // Set the fadeout animation on death
     if player_was_alive && !state.player.alive() {
+        let tip = {
+            let death = tips::Death::new(state);
+            tips::pick(&tips::relevant_tips(&death), &state.shown_tips)
+        };
+        state.death_tip = tip;
+        state.shown_tips.extend(tip);
+
         use player::CauseOfDeath::*;
         let cause_of_death = formula::cause_of_death(&state.player);
         let fade_color = match cause_of_death {
@@ -319,7 +458,7 @@ pub fn update(
         }
     }
 
-    render::render_game(&state, dt, fps, drawcalls);
+    render::render_game(&state, dt, fps, settings, drawcalls);
 
     let drawcall_duration = drawcall_stopwatch.finish();
     state.stats.push(FrameStats {
@@ -334,7 +473,10 @@ fn process_monsters<R: Rng>(
     world: &mut World,
     player: &mut player::Player,
     area: Rectangle,
+    loaded_area: Rectangle,
     rng: &mut R,
+    messages: &mut MessageLog,
+    balance: &Balance,
 ) {
     if !player.alive() {
         return;
@@ -343,6 +485,23 @@ fn process_monsters<R: Rng>(
     let monster_count_estimate = area.dimensions().x * area.dimensions().y / 4;
     assert!(monster_count_estimate > 0);
     let mut monster_positions_vec = world.monsters(area).map(|m| m.position).collect::<Vec<_>>();
+    // NOTE: the companion follows the player even when it's outside
+    // of the simulation area.
+    if let Some(companion) = player.companion.and_then(|id| world.monster(id)) {
+        if companion.alive() && !area.contains(companion.position) {
+            monster_positions_vec.push(companion.position);
+        }
+    }
+    // NOTE: so do the monsters chasing the player. The coarse
+    // simulation would make them lose track of them.
+    let chasing_monsters = world
+        .monsters(loaded_area)
+        .filter(|m| {
+            m.ai_state == AIState::Chasing && !m.accompanying_player && !area.contains(m.position)
+        })
+        .map(|m| m.position)
+        .collect::<Vec<_>>();
+    monster_positions_vec.extend(chasing_monsters);
     // TODO: Sort by how far it is from the player?
     // NOTE: `world.monsters` does not give a stable result so we need to sort
     // it here to ensure correct replays.
@@ -365,14 +524,14 @@ fn process_monsters<R: Rng>(
             mind: player.mind,
             pos: player.pos,
         };
-        let action = {
-            let (update, action) = monster_readonly.act(player_info, world, rng);
+        let (ai_state, action) = {
+            let (update, action) = monster_readonly.act(player_info, world, rng, balance);
             if let Some(monster) = world.monster_on_pos(monster_position) {
                 monster.ai_state = update.ai_state;
                 monster.max_ap = update.max_ap;
                 monster.spend_ap(1);
             }
-            action
+            (update.ai_state, action)
         };
 
         match action {
@@ -381,6 +540,14 @@ fn process_monsters<R: Rng>(
 
                 let pos = monster_readonly.position;
 
+                // NOTE: only the monsters chasing the player cut
+                // their way through the trees. The rest go around.
+                let blockers = if ai_state == AIState::Chasing {
+                    monster_readonly.blockers
+                } else {
+                    monster_readonly.blockers | Blocker::WALL
+                };
+
                 let path_changed = monster_readonly
                     .path
                     .last()
@@ -394,7 +561,7 @@ fn process_monsters<R: Rng>(
                 let (newpos, newpath) = if monster_readonly.path.is_empty() || path_changed ||
                     !world.walkable(
                         monster_readonly.path[0],
-                        monster_readonly.blockers,
+                        blockers,
                         player.pos,
                     )
                 {
@@ -403,30 +570,48 @@ fn process_monsters<R: Rng>(
                         pos,
                         destination,
                         world,
-                        monster_readonly.blockers,
+                        blockers,
                         player.pos,
                     );
                     let newpos = path.next().unwrap_or(pos);
                     // Cache the path-finding result
-                    let newpath = path.collect();
+                    let newpath: Vec<Point> = path.collect();
                     (newpos, newpath)
                 } else {
                     (monster_readonly.path[0], monster_readonly.path[1..].into())
                 };
 
-                world.move_monster(pos, newpos, player.pos);
-                if let Some(monster) = world.monster_on_pos(newpos) {
-                    monster.path = newpath;
-                    if monster.has_ap(1) {
-                        monster.trail = Some(newpos);
+                // NOTE: monsters that can walk through trees have to
+                // cut them down first. That takes up the whole move
+                // and they step in on the next one.
+                let tree_in_the_way = world.cell(newpos).map_or(false, |cell| {
+                    cell.tile.kind == TileKind::Tree
+                });
+                if tree_in_the_way {
+                    if let Some(cell) = world.cell_mut(newpos) {
+                        cell.tile = Tile::new(TileKind::Empty);
+                    }
+                    if let Some(monster) = world.monster_on_pos(pos) {
+                        let mut path = vec![newpos];
+                        path.extend(newpath);
+                        monster.path = path;
+                    }
+                } else {
+                    world.move_monster(pos, newpos, player.pos);
+                    if let Some(monster) = world.monster_on_pos(newpos) {
+                        monster.path = newpath;
+                        if monster.has_ap(1) {
+                            monster.trail = Some(newpos);
+                        }
                     }
+                    monster_position = newpos;
                 }
-                monster_position = newpos;
             }
 
             Action::Attack(target_pos, damage) => {
                 assert!(target_pos == player.pos);
-                player.take_effect(damage);
+                messages.add(Event::Attacked(monster_readonly.kind));
+                take_effect(player, damage, messages, balance);
                 if monster_readonly.die_after_attack {
                     kill_monster(monster_readonly.position, world);
                 }
@@ -437,7 +622,7 @@ fn process_monsters<R: Rng>(
                 }
             }
 
-            Action::Use(_) => unreachable!(),
+            Action::Use(_) | Action::Wait => unreachable!(),
         }
 
         if world.monster_on_pos(monster_position).map_or(
@@ -452,14 +637,108 @@ fn process_monsters<R: Rng>(
 }
 
 
+/// Coarse simulation of the loaded world outside of the
+/// `simulation_area`. Monsters wander about and regroup (without any
+/// pathfinding) and food respawns.
+///
+/// This uses its own rng derived from the game seed and the current
+/// turn so it stays deterministic for the replays.
+fn process_offscreen_world(
+    world: &mut World,
+    player: &player::Player,
+    simulation_area: Rectangle,
+    loaded_area: Rectangle,
+    chunk_size: i32,
+    seed: u32,
+    turn: i32,
+    balance: &Balance,
+) {
+    let rng_seed: &[_] = &[seed, turn as u32];
+    let mut rng: IsaacRng = SeedableRng::from_seed(rng_seed);
+
+    // NOTE: The companion and the chasing monsters are always
+    // processed by `process_monsters`.
+    let mut monster_ids = world
+        .monsters(loaded_area)
+        .filter(|m| {
+            !simulation_area.contains(m.position) && !m.accompanying_player &&
+                m.ai_state != AIState::Chasing
+        })
+        .map(|m| m.id)
+        .collect::<Vec<_>>();
+    // NOTE: `world.monsters` does not give a stable result so we need to sort
+    // it here to ensure correct replays.
+    monster_ids.sort();
+
+    let blockers = Blocker::PLAYER | Blocker::MONSTER | Blocker::WALL;
+    for id in monster_ids {
+        let monster = match world.monster(id) {
+            Some(monster) => monster.clone(),
+            None => continue,
+        };
+        let destination = ai::coarse_destination(&monster, world, &mut rng, player.pos, balance);
+        if destination != monster.position && world.walkable(destination, blockers, player.pos) {
+            world.move_monster(monster.position, destination, player.pos);
+            if let Some(monster) = world.monster_mut(id) {
+                // NOTE: any cached path or target is stale now.
+                monster.path.clear();
+                monster.ai_state = AIState::Idle;
+            }
+        }
+    }
+
+    let food = {
+        let item_archetypes = world.item_archetypes();
+        item_archetypes.item(item_archetypes.food())
+    };
+    let mut chunk_positions = world.positions_of_all_chunks();
+    chunk_positions.sort_by_key(|pos| (pos.x, pos.y));
+    for chunk_position in chunk_positions {
+        let chunk_area = Rectangle::from_point_and_size(chunk_position, Point::from_i32(chunk_size));
+        if chunk_area.intersects(simulation_area) {
+            continue;
+        }
+        if rng.gen_range(0, 100) >= balance.offscreen_food_respawn_chance {
+            continue;
+        }
+        // NOTE: don't let the food pile up. A chunk never gets more
+        // of it than the generator would have put there.
+        let (empty_tiles, food_count) = match world.chunk(chunk_position) {
+            Some(chunk) => chunk.cells().fold((0, 0), |(empty_tiles, food_count), (_, cell)| {
+                if cell.tile.kind == TileKind::Empty {
+                    let food_here = cell.items.iter().filter(|item| item.kind == food.kind).count();
+                    (empty_tiles + 1, food_count + food_here as u32)
+                } else {
+                    (empty_tiles, food_count)
+                }
+            }),
+            None => continue,
+        };
+        if food_count >= world.item_archetypes().expected_count(food.kind, empty_tiles) {
+            continue;
+        }
+        let pos = chunk_position + (rng.gen_range(0, chunk_size), rng.gen_range(0, chunk_size));
+        if world.walkable(pos, Blocker::WALL, player.pos) {
+            if let Some(cell) = world.cell_mut(pos) {
+                if cell.items.is_empty() {
+                    cell.items.push(food);
+                }
+            }
+        }
+    }
+}
+
+
 fn process_player_action<R, W>(
     player: &mut player::Player,
     commands: &mut VecDeque<Command>,
+    recent_commands: &mut VecDeque<Command>,
     world: &mut World,
-    simulation_area: Rectangle,
     explosion_animation: &mut Option<Box<AreaOfEffect>>,
     rng: &mut R,
     command_logger: &mut W,
+    messages: &mut MessageLog,
+    balance: &Balance,
 ) where
     R: Rng,
     W: Write,
@@ -470,6 +749,11 @@ fn process_player_action<R, W>(
 
     if let Some(command) = commands.pop_front() {
         state::log_command(command_logger, command);
+        recent_commands.push_back(command);
+        if recent_commands.len() > state::RECENT_COMMANDS_COUNT {
+            recent_commands.pop_front();
+        }
+        player.drawn_to_dose = false;
         let mut action = match command {
             Command::N => Action::Move(player.pos + (0, -1)),
             Command::S => Action::Move(player.pos + (0, 1)),
@@ -481,23 +765,20 @@ fn process_player_action<R, W>(
             Command::SW => Action::Move(player.pos + (-1, 1)),
             Command::SE => Action::Move(player.pos + (1, 1)),
 
-            Command::UseFood => Action::Use(item::Kind::Food),
-            Command::UseDose => Action::Use(item::Kind::Dose),
-            Command::UseCardinalDose => Action::Use(item::Kind::CardinalDose),
-            Command::UseDiagonalDose => Action::Use(item::Kind::DiagonalDose),
-            Command::UseStrongDose => Action::Use(item::Kind::StrongDose),
+            Command::UseItem(kind) => Action::Use(kind),
+            Command::Wait => Action::Wait,
         };
 
-        if *player.stun > 0 {
-            action = Action::Move(player.pos);
-        } else if *player.panic > 0 {
-            let new_pos =
-                world.random_neighbour_position(rng, player.pos, Blocker::WALL, player.pos);
-            action = Action::Move(new_pos);
-
+        if let Some(filter) = player.effects.action_filter() {
+            action = match filter {
+                ActionFilter::StayInPlace => Action::Move(player.pos),
+                ActionFilter::RandomDirection => {
+                    let pos = player.pos;
+                    Action::Move(world.random_neighbour_position(rng, pos, Blocker::WALL, pos))
+                }
+            };
         } else if let Some((dose_pos, dose)) = world.nearest_dose(player.pos, 5) {
-            let resist_radius = formula::player_resist_radius(dose.irresistible, *player.will) as
-                usize;
+            let resist_radius = player.resist_radius(&dose) as usize;
             if player.pos.tile_distance(dose_pos) < resist_radius as i32 {
                 let mut path =
                     pathfinding::Path::find(player.pos, dose_pos, world, Blocker::WALL, player.pos);
@@ -510,6 +791,7 @@ fn process_player_action<R, W>(
 
                 if let Some(new_pos) = new_pos_opt {
                     action = Action::Move(new_pos);
+                    player.drawn_to_dose = true;
                 } else {
                     // NOTE: no path leading to the irresistable dose
                 }
@@ -521,9 +803,7 @@ fn process_player_action<R, W>(
         let carried_irresistible_dose = player
             .inventory
             .iter()
-            .find(|i| {
-                i.is_dose() && formula::player_resist_radius(i.irresistible, *player.will) > 0
-            })
+            .find(|i| i.is_dose() && player.resist_radius(i) > 0)
             .map(|i| i.kind);
         if let Some(kind) = carried_irresistible_dose {
             action = Action::Use(kind);
@@ -534,176 +814,450 @@ fn process_player_action<R, W>(
                 let dest_walkable =
                     world.walkable(dest, Blocker::WALL | Blocker::MONSTER, player.pos);
                 let bumping_into_monster = world.monster_on_pos(dest).is_some();
+                let bumping_into_tree = world.cell(dest).map_or(false, |cell| {
+                    cell.tile.kind == TileKind::Tree
+                });
+                let gun_targets = if player.has_equipment(Equipment::Gun) && !bumping_into_monster {
+                    gun_targets(player.pos, dest, world)
+                } else {
+                    vec![]
+                };
                 if bumping_into_monster {
                     player.spend_ap(1);
                     // println!("Player attacks {:?}", monster);
                     if let Some(kind) = world.monster_on_pos(dest).map(|m| m.kind) {
                         match kind {
                             monster::Kind::Anxiety => {
-                                println!("Bumped into anxiety! Current anxiety counter: {:?}", player.anxiety_counter);
                                 let increment = if player.bonuses.contains(&CompanionBonus::DoubleWillGrowth) {
                                     2
                                 } else {
                                     1
                                 };
-                                println!("Anxiety increment: {:?}", increment);
                                 player.anxiety_counter += increment;
-                                println!("New anxiety counter: {:?}", player.anxiety_counter);
                                 if player.anxiety_counter.is_max() {
-                                    println!("Increasing player's will");
                                     player.will += 1;
                                     player.anxiety_counter.set_to_min();
+                                    messages.add(Event::WillIncreased(*player.will));
                                 }
                             }
                             // NOTE: NPCs don't give bonuses or accompany the player when high.
                             monster::Kind::Npc if player.mind.is_sober() => {
-                                println!("Bumped into NPC: {:?}", world.monster_on_pos(dest));
                                 // Clear any existing monsters accompanying the player. The player
                                 // can have only one companion at a time right now.
-                                //
-                                // TODO: it also sounds like we could just track the followers in
-                                // the Player/State struct but that needs Monster IDs.
-                                let npcs = world
-                                    .monsters_mut(simulation_area)
-                                    .filter(|m| m.kind == monster::Kind::Npc);
-                                for npc in npcs {
-                                    if npc.position == dest {
-                                        println!("NPC {:?} accompanies the player.", npc);
-                                        npc.accompanying_player = true;
-                                        assert!(npc.companion_bonus.is_some());
-                                    } else if npc.accompanying_player {
-                                        println!("NPC {:?} leaves the player.", npc);
+                                if let Some(previous_companion) = player.companion.take() {
+                                    if let Some(npc) = world.monster_mut(previous_companion) {
                                         npc.accompanying_player = false;
+                                        messages.add(Event::CompanionLeft);
                                     }
                                 }
+                                if let Some(npc) = world.monster_on_pos(dest) {
+                                    npc.accompanying_player = true;
+                                    assert!(npc.companion_bonus.is_some());
+                                    player.companion = Some(npc.id);
+                                    messages.add(Event::CompanionJoined(npc.companion_bonus));
+                                }
                             }
                             _ => {}
                         }
                         kill_monster(dest, world);
+                        if world.monster_on_pos(dest).is_none() {
+                            messages.add(Event::Defeated(kind));
+                        }
+                    }
+
+                } else if !gun_targets.is_empty() {
+                    player.spend_ap(1);
+                    for target in gun_targets {
+                        if let Some(kind) = world.monster_on_pos(target).map(|m| m.kind) {
+                            kill_monster(target, world);
+                            if world.monster_on_pos(target).is_none() {
+                                messages.add(Event::Defeated(kind));
+                            }
+                        }
                     }
 
                 } else if dest_walkable {
                     player.spend_ap(1);
                     player.move_to(dest);
-                    while let Some(item) = world.pickup_item(dest) {
-                        use item::Kind::*;
-                        match item.kind {
-                            Food => player.inventory.push(item),
-                            Dose | StrongDose | CardinalDose | DiagonalDose => {
-                                if formula::player_resist_radius(
-                                    item.irresistible,
-                                    *player.will,
-                                ) == 0
-                                {
-                                    player.inventory.push(item);
-                                } else {
-                                    use_dose(player, explosion_animation, item);
-                                }
+                    if let Some(equipment) = world.pickup_equipment(dest) {
+                        if player.equip(equipment) {
+                            messages.add(Event::Equipped(equipment));
+                        } else {
+                            // NOTE: the slot's taken, leave it where it was
+                            if let Some(cell) = world.cell_mut(dest) {
+                                cell.equipment = Some(equipment);
                             }
                         }
                     }
+                    while let Some(item) = world.pickup_item(dest) {
+                        let irresistible = item.is_dose() && player.resist_radius(&item) > 0;
+                        if irresistible {
+                            player.drawn_to_dose = true;
+                            use_item(player, explosion_animation, item, messages, balance);
+                        } else {
+                            player.inventory.push(item);
+                        }
+                    }
+                } else if bumping_into_tree && player.has_equipment(Equipment::Pickaxe) {
+                    player.spend_ap(1);
+                    if let Some(cell) = world.cell_mut(dest) {
+                        cell.tile = Tile::new(TileKind::Empty);
+                    }
+                    messages.add(Event::TreeCutDown);
                 } else {
                     // NOTE: we bumped into a wall, don't do anything
                 }
             }
 
-            Action::Use(item::Kind::Food) => {
-                if let Some(food_idx) = player.inventory.iter().position(
-                    |&i| i.kind == item::Kind::Food,
-                )
-                {
+            Action::Use(kind) => {
+                if let Some(item_index) = player.inventory.iter().position(|&i| i.kind == kind) {
                     player.spend_ap(1);
-                    let food = player.inventory.remove(food_idx);
-                    player.take_effect(food.modifier);
-                    let food_explosion_radius = 2;
-                    let animation = animation::SquareExplosion::new(
-                        player.pos,
-                        food_explosion_radius,
-                        1,
-                        color::explosion,
-                    );
-                    *explosion_animation = Some(Box::new(animation));
+                    let item = player.inventory.remove(item_index);
+                    use_item(player, explosion_animation, item, messages, balance);
                 }
             }
 
-            Action::Use(item::Kind::Dose) => {
-                if let Some(dose_index) = player.inventory.iter().position(
-                    |&i| i.kind == item::Kind::Dose,
-                )
-                {
-                    player.spend_ap(1);
-                    let dose = player.inventory.remove(dose_index);
-                    use_dose(player, explosion_animation, dose);
-                }
+            Action::Wait => {
+                player.spend_ap(1);
             }
 
-            Action::Use(item::Kind::StrongDose) => {
-                if let Some(dose_index) = player.inventory.iter().position(|&i| {
-                    i.kind == item::Kind::StrongDose
-                })
-                {
-                    player.spend_ap(1);
-                    let dose = player.inventory.remove(dose_index);
-                    use_dose(player, explosion_animation, dose);
-                }
+            Action::Attack(_, _) => {
+                unreachable!();
             }
+        }
+    }
+}
 
-            Action::Use(item::Kind::CardinalDose) => {
-                if let Some(dose_index) = player.inventory.iter().position(|&i| {
-                    i.kind == item::Kind::CardinalDose
-                })
-                {
-                    player.spend_ap(1);
-                    let dose = player.inventory.remove(dose_index);
-                    use_dose(player, explosion_animation, dose);
+/// The positions of the monsters the gun hits when the player moves
+/// from `pos` towards `dest`. It only goes off when there's a monster
+/// other than a person within the range. Trees stop the shot.
+fn gun_targets(pos: Point, dest: Point, world: &mut World) -> Vec<Point> {
+    let direction = dest - pos;
+    let mut targets = vec![];
+    let mut hostile_in_range = false;
+    let mut target = pos;
+    for _ in 0..item::GUN_RANGE {
+        target = target + direction;
+        if !world.walkable(target, Blocker::WALL, pos) {
+            break;
+        }
+        if let Some(kind) = world.monster_on_pos(target).map(|m| m.kind) {
+            targets.push(target);
+            if kind != monster::Kind::Npc {
+                hostile_in_range = true;
+            }
+        }
+    }
+    if hostile_in_range {
+        targets
+    } else {
+        vec![]
+    }
+}
+
+/// Where the travel `action` would take the player right now.
+fn travel_goal(action: GameAction, player: &player::Player, world: &World) -> Option<TravelGoal> {
+    let radius = formula::exploration_radius(player.mind);
+    match action {
+        GameAction::Explore => Some(TravelGoal::Explore),
+        GameAction::TravelToFood => {
+            let food = world.item_archetypes().food();
+            world
+                .nearest_item(player.pos, radius, |item| item.kind == food)
+                .map(|(pos, _food)| TravelGoal::Item(pos))
+        }
+        GameAction::TravelToDose => {
+            world
+                .nearest_dose(player.pos, radius)
+                .map(|(pos, _dose)| TravelGoal::Item(pos))
+        }
+        GameAction::TravelToCompanion => {
+            // NOTE: NPCs don't accompany the player when high
+            if player.mind.is_high() {
+                return None;
+            }
+            let area = Rectangle::center(player.pos, Point::from_i32(radius));
+            world
+                .monsters(area)
+                .filter(|m| m.alive() && m.companion_bonus.is_some() && !m.accompanying_player)
+                .filter(|m| m.position.distance(player.pos) < (radius as f32))
+                .min_by_key(|m| (player.pos.tile_distance(m.position), m.id))
+                .map(|m| TravelGoal::Companion(m.id))
+        }
+        _ => None,
+    }
+}
+
+fn start_travel(state: &mut State, goal: TravelGoal) {
+    state.rest = None;
+    state.travel = Some(Travel {
+        goal,
+        known_monsters: visible_monsters(&state.player, &state.world),
+        known_items: visible_items(&state.player, &state.world),
+        mind: state.player.mind,
+    });
+}
+
+/// Queue up the next step of the player's travel. Stop travelling
+/// when they've arrived or when something happened that they should
+/// react to.
+fn process_travel(state: &mut State) {
+    let goal = match state.travel {
+        Some(ref travel) => {
+            if let Some(reason) = travel_interruption(&state.player, &state.world, travel) {
+                if state.player.alive() {
+                    state.messages.add(Event::TravelInterrupted(reason));
                 }
+                None
+            } else {
+                Some(travel.goal)
             }
+        }
+        None => return,
+    };
 
-            Action::Use(item::Kind::DiagonalDose) => {
-                if let Some(dose_index) = player.inventory.iter().position(|&i| {
-                    i.kind == item::Kind::DiagonalDose
-                })
-                {
-                    player.spend_ap(1);
-                    let dose = player.inventory.remove(dose_index);
-                    use_dose(player, explosion_animation, dose);
+    let player_pos = state.player.pos;
+    let blockers = Blocker::WALL | Blocker::MONSTER;
+    let next_pos = match goal {
+        Some(TravelGoal::Destination(destination)) => {
+            pathfinding::Path::find(player_pos, destination, &state.world, blockers, player_pos)
+                .next()
+        }
+        Some(TravelGoal::Item(item_pos)) => {
+            let item_there = state.world.cell(item_pos).map_or(false, |cell| !cell.items.is_empty());
+            if item_there {
+                pathfinding::Path::find(player_pos, item_pos, &state.world, blockers, player_pos)
+                    .next()
+            } else {
+                None
+            }
+        }
+        Some(TravelGoal::Companion(id)) => {
+            let npc_pos = state.world.monster(id).and_then(|npc| {
+                if npc.alive() && !npc.accompanying_player {
+                    Some(npc.position)
+                } else {
+                    None
                 }
+            });
+            match npc_pos {
+                // NOTE: bumping into the NPC is what makes them accompany the player
+                Some(npc_pos) if player_pos.tile_distance(npc_pos) == 1 => Some(npc_pos),
+                Some(npc_pos) => {
+                    let next = pathfinding::Path::find(
+                        player_pos,
+                        npc_pos,
+                        &state.world,
+                        Blocker::WALL,
+                        player_pos,
+                    ).next();
+                    // NOTE: don't attack whoever's standing in the way
+                    match next {
+                        Some(pos) if state.world.walkable(pos, blockers, player_pos) => Some(pos),
+                        _ => None,
+                    }
+                }
+                None => None,
             }
+        }
+        Some(TravelGoal::Explore) => {
+            // NOTE: only look within the simulation area, that's
+            // where the chunks are loaded.
+            let area = state.simulation_area();
+            let world = &state.world;
+            let unexplored = |pos| world.cell(pos).map_or(false, |cell| !cell.explored);
+            let result = pathfinding::nearest(player_pos, world, blockers, player_pos, area, unexplored);
+            if result.is_none() {
+                state.messages.add(Event::NothingToExplore);
+            }
+            result.map(|(_unexplored_pos, first_step)| first_step)
+        }
+        None => None,
+    };
 
-            Action::Attack(_, _) => {
-                unreachable!();
+    match next_pos.and_then(|next_pos| step_command(player_pos, next_pos)) {
+        Some(command) => state.commands.push_back(command),
+        None => state.travel = None,
+    }
+}
+
+fn start_rest(state: &mut State, turns: i32) {
+    state.travel = None;
+    state.rest = Some(Rest {
+        turns_left: turns,
+        known_monsters: visible_monsters(&state.player, &state.world),
+        until_recovered: state.player.effects.action_filter().is_some(),
+    });
+}
+
+/// Pass another turn if the player is resting and nothing happened
+/// that would wake them up.
+fn process_rest(state: &mut State) {
+    let stop_reason = match state.rest {
+        Some(ref rest) => rest_interruption(&state.player, &state.world, rest),
+        None => return,
+    };
+
+    match stop_reason {
+        Some(reason) => {
+            if state.player.alive() {
+                state.messages.add(Event::StoppedResting(reason));
             }
+            state.rest = None;
+        }
+        None => {
+            state.commands.push_back(Command::Wait);
+            if let Some(ref mut rest) = state.rest {
+                rest.turns_left -= 1;
+            }
+        }
+    }
+}
+
+/// Why the player should stop resting, if at all.
+fn rest_interruption(player: &player::Player, world: &World, rest: &Rest) -> Option<&'static str> {
+    if !player.alive() {
+        return Some("you died");
+    }
+    if rest.turns_left <= 0 {
+        return Some("you rested long enough");
+    }
+    let lost_control = player.effects.action_filter().is_some();
+    if rest.until_recovered && !lost_control {
+        return Some("you recovered");
+    }
+    if !rest.until_recovered && lost_control {
+        return Some("you were attacked");
+    }
+    if irresistible_dose_nearby(player, world).is_some() {
+        return Some("a dose is pulling you in");
+    }
+    let new_monster = visible_monsters(player, world)
+        .iter()
+        .any(|id| !rest.known_monsters.contains(id));
+    if new_monster {
+        return Some("a monster came into view");
+    }
+    None
+}
+
+/// Why the player should stop travelling, if at all.
+fn travel_interruption(
+    player: &player::Player,
+    world: &World,
+    travel: &Travel,
+) -> Option<&'static str> {
+    if !player.alive() {
+        return Some("you died");
+    }
+    if player.effects.action_filter().is_some() {
+        return Some("you lost control");
+    }
+    if let Some(dose_pos) = irresistible_dose_nearby(player, world) {
+        // NOTE: that's where the player's going anyway
+        if travel.goal != TravelGoal::Item(dose_pos) {
+            return Some("a dose is pulling you in");
+        }
+    }
+    let new_monster = visible_monsters(player, world)
+        .iter()
+        .any(|id| !travel.known_monsters.contains(id));
+    if new_monster {
+        return Some("a monster came into view");
+    }
+
+    // NOTE: when the player picked the destination themself, they
+    // know what they're doing. Auto-travel should be more careful.
+    if let TravelGoal::Destination(_) = travel.goal {
+        return None;
+    }
+    if !player.mind.same_state(&travel.mind) {
+        return Some("your state of mind changed");
+    }
+    if travel.goal == TravelGoal::Explore {
+        let new_item = visible_items(player, world)
+            .iter()
+            .any(|pos| !travel.known_items.contains(pos));
+        if new_item {
+            return Some("you found an item");
         }
     }
+    None
+}
+
+/// The positions of the items within the player's field of view.
+fn visible_items(player: &player::Player, world: &World) -> Vec<Point> {
+    let radius = formula::exploration_radius(player.mind);
+    CircularArea::new(player.pos, radius)
+        .filter(|&pos| world.cell(pos).map_or(false, |cell| !cell.items.is_empty()))
+        .collect()
+}
+
+/// The monsters within the player's field of view. The companion
+/// doesn't count.
+fn visible_monsters(player: &player::Player, world: &World) -> Vec<MonsterId> {
+    let radius = formula::exploration_radius(player.mind);
+    let area = Rectangle::center(player.pos, Point::from_i32(radius));
+    let mut result = world
+        .monsters(area)
+        .filter(|m| m.alive() && !m.accompanying_player)
+        .filter(|m| m.position.distance(player.pos) < (radius as f32))
+        .map(|m| m.id)
+        .collect::<Vec<_>>();
+    result.sort();
+    result
 }
 
-fn process_player(state: &mut State, simulation_area: Rectangle) {
+/// The position of the dose that's close enough to pull the player
+/// in, if any.
+fn irresistible_dose_nearby(player: &player::Player, world: &World) -> Option<Point> {
+    world.nearest_dose(player.pos, 5).and_then(|(dose_pos, dose)| {
+        let resist_radius = player.resist_radius(&dose);
+        if player.pos.tile_distance(dose_pos) < resist_radius {
+            Some(dose_pos)
+        } else {
+            None
+        }
+    })
+}
+
+/// The movement command that gets the player from `from` to the
+/// neighbouring tile `to`.
+fn step_command(from: Point, to: Point) -> Option<Command> {
+    match (to.x - from.x, to.y - from.y) {
+        (0, -1) => Some(Command::N),
+        (0, 1) => Some(Command::S),
+        (-1, 0) => Some(Command::W),
+        (1, 0) => Some(Command::E),
+        (-1, -1) => Some(Command::NW),
+        (1, -1) => Some(Command::NE),
+        (-1, 1) => Some(Command::SW),
+        (1, 1) => Some(Command::SE),
+        _ => None,
+    }
+}
+
+fn process_player(state: &mut State) {
     { // appease borrowck
         let player = &mut state.player;
 
         // NPCs should unfollow an intoxicated player:
         if player.mind.is_high() {
-            let npcs = state.world.monsters_mut(simulation_area)
-                .filter(|m| m.kind == monster::Kind::Npc && m.accompanying_player
-                        && m.companion_bonus.is_some());
-            for npc in npcs {
-                println!("{:?} will not accompany an intoxicated player.", npc);
-                npc.accompanying_player = false;
+            if let Some(npc_id) = player.companion.take() {
+                if let Some(npc) = state.world.monster_mut(npc_id) {
+                    npc.accompanying_player = false;
+                    state.messages.add(Event::CompanionLeft);
+                }
             }
         }
 
         let world = &state.world;
 
-        // TODO: this will stop the bonus from working once the
-        // companion NPC leaves the simulation_area. Which is
-        // currently possible because it doesn't follow the player
-        // around.
-        let npc_bonuses = world.monsters(simulation_area)
-            .filter(|m| m.kind == monster::Kind::Npc && m.accompanying_player
-                    && m.companion_bonus.is_some())
-            .map(|m| m.companion_bonus.unwrap());
+        let npc_bonus = player.companion
+            .and_then(|npc_id| world.monster(npc_id))
+            .and_then(|npc| if npc.alive() { npc.companion_bonus } else { None });
         player.bonuses.clear();
-        player.bonuses.extend(npc_bonuses);
+        player.bonuses.extend(npc_bonus);
     }
 
     let previous_action_points = state.player.ap();
@@ -711,11 +1265,13 @@ fn process_player(state: &mut State, simulation_area: Rectangle) {
     process_player_action(
         &mut state.player,
         &mut state.commands,
+        &mut state.recent_commands,
         &mut state.world,
-        simulation_area,
         &mut state.explosion_animation,
         &mut state.rng,
         &mut state.command_logger,
+        &mut state.messages,
+        &state.balance,
     );
 
     let spent_ap_this_turn = previous_action_points > state.player.ap();
@@ -723,6 +1279,15 @@ fn process_player(state: &mut State, simulation_area: Rectangle) {
     // Increase the sobriety counter if the player behaved themself.
     if spent_ap_this_turn && !state.player.mind.is_high() && state.player.will.is_max() {
         state.player.sobriety_counter += 1;
+        let earned = formula::sobriety_token_earned(
+            state.player.sobriety_counter,
+            state.player.sobriety_tokens,
+            &state.balance,
+        );
+        if earned {
+            state.player.take_effect(player::Modifier::SobrietyToken, &state.balance);
+            state.messages.add(Event::SobrietyToken(state.player.sobriety_tokens));
+        }
     }
 
     // Set the longest high streak
@@ -750,123 +1315,201 @@ fn process_player(state: &mut State, simulation_area: Rectangle) {
 }
 
 
-fn process_keys(keys: &mut Keys, commands: &mut VecDeque<Command>) {
-    use keys::KeyCode::*;
+/// Turn the pressed keys into commands. If the player asked to
+/// travel somewhere or rest, return that action.
+fn process_keys(
+    keys: &mut Keys,
+    commands: &mut VecDeque<Command>,
+    key_bindings: &KeyBindings,
+    replay: bool,
+    item_archetypes: &ItemArchetypes,
+) -> Option<GameAction> {
+    let mut auto_action = None;
     while let Some(key) = keys.get() {
-        match key {
-            // Numpad (8246 for cardinal and 7193 for diagonal movement)
-            Key { code: NumPad8, .. } => commands.push_back(Command::N),
-            Key { code: NumPad2, .. } => commands.push_back(Command::S),
-            Key { code: NumPad4, .. } => commands.push_back(Command::W),
-            Key { code: NumPad6, .. } => commands.push_back(Command::E),
-            Key { code: NumPad7, .. } => commands.push_back(Command::NW),
-            Key { code: NumPad1, .. } => commands.push_back(Command::SW),
-            Key { code: NumPad9, .. } => commands.push_back(Command::NE),
-            Key { code: NumPad3, .. } => commands.push_back(Command::SE),
-
-            // NotEye (arrow keys plus Ctrl and Shift modifiers for
-            // horizontal movement)
-            Key { code: Up, .. } => commands.push_back(Command::N),
-            Key { code: Down, .. } => commands.push_back(Command::S),
-            Key {
-                code: Left,
-                ctrl: false,
-                shift: true,
-                ..
-            } => commands.push_back(Command::NW),
-            Key {
-                code: Left,
-                ctrl: true,
-                shift: false,
-                ..
-            } => commands.push_back(Command::SW),
-            Key { code: Left, .. } => commands.push_back(Command::W),
-            Key {
-                code: Right,
-                ctrl: false,
-                shift: true,
-                ..
-            } => commands.push_back(Command::NE),
-            Key {
-                code: Right,
-                ctrl: true,
-                shift: false,
-                ..
-            } => commands.push_back(Command::SE),
-            Key { code: Right, .. } => commands.push_back(Command::E),
-
-            // Vi keys (hjkl for cardinal and yunm for diagonal movement)
-            Key { code: K, .. } => commands.push_back(Command::N),
-            Key { code: J, .. } => commands.push_back(Command::S),
-            Key { code: H, .. } => commands.push_back(Command::W),
-            Key { code: L, .. } => commands.push_back(Command::E),
-            Key { code: Y, .. } => commands.push_back(Command::NW),
-            Key { code: N, .. } => commands.push_back(Command::SW),
-            Key { code: U, .. } => commands.push_back(Command::NE),
-            Key { code: M, .. } => commands.push_back(Command::SE),
-
-            // Non-movement commands
-            Key { code: E, .. } |
-            Key { code: D1, .. } => {
-                commands.push_back(Command::UseFood);
-            }
-            Key { code: D2, .. } => {
-                commands.push_back(Command::UseDose);
+        let action = match key_bindings.action(key, replay) {
+            Some(keymap::Action::Game(action)) => action,
+            // NOTE: the meta actions are handled in `update`
+            _ => continue,
+        };
+        let command = match action {
+            GameAction::N => Some(Command::N),
+            GameAction::S => Some(Command::S),
+            GameAction::W => Some(Command::W),
+            GameAction::E => Some(Command::E),
+            GameAction::NW => Some(Command::NW),
+            GameAction::NE => Some(Command::NE),
+            GameAction::SW => Some(Command::SW),
+            GameAction::SE => Some(Command::SE),
+            GameAction::Eat => Some(Command::UseItem(item_archetypes.food())),
+            GameAction::UseInventory(inventory_key) => {
+                item_archetypes
+                    .kind_by_inventory_key(inventory_key)
+                    .map(Command::UseItem)
             }
-            Key { code: D3, .. } => {
-                commands.push_back(Command::UseStrongDose);
+            GameAction::Explore |
+            GameAction::TravelToFood |
+            GameAction::TravelToDose |
+            GameAction::TravelToCompanion |
+            GameAction::Rest => {
+                auto_action = Some(action);
+                None
             }
-            _ => {
-                match inventory_commands(key) {
-                    Some(command) => commands.push_back(command),
-                    None => (),
+            GameAction::Wait => Some(Command::Wait),
+        };
+        if let Some(command) = command {
+            commands.push_back(command);
+        }
+    }
+    auto_action
+}
+
+/// Return true if any of the queued up keys is bound to the `action`
+/// (and take it out of the queue).
+fn meta_action_pressed(
+    keys: &mut Keys,
+    key_bindings: &KeyBindings,
+    replay: bool,
+    action: MetaAction,
+) -> bool {
+    keys.matches(|key| key_bindings.action(key, replay) == Some(keymap::Action::Meta(action)))
+}
+
+/// Handle the keys pressed while the key bindings screen is open.
+///
+/// These are fixed (arrows, Enter, Esc, ...) rather than coming from
+/// the bindings so the player can always fix a broken setup.
+fn process_key_bindings_screen(state: &mut State, settings: &mut Settings) {
+    let actions = keymap::Action::all();
+    while let Some(key) = state.keys.get() {
+        let mut close = false;
+        if let Some(ref mut screen) = state.key_bindings_screen {
+            let selected_action = actions[screen.selected];
+            if screen.waiting_for_key {
+                screen.waiting_for_key = false;
+                if key.code != KeyCode::Esc {
+                    match settings.key_bindings.bind(key, selected_action) {
+                        Ok(()) => settings.save(),
+                        Err(message) => screen.message = Some(message),
+                    }
+                }
+            } else {
+                screen.message = None;
+                match key.code {
+                    KeyCode::Up => {
+                        screen.selected = (screen.selected + actions.len() - 1) % actions.len();
+                    }
+                    KeyCode::Down => {
+                        screen.selected = (screen.selected + 1) % actions.len();
+                    }
+                    KeyCode::Enter => screen.waiting_for_key = true,
+                    KeyCode::C => {
+                        match settings.key_bindings.clear(selected_action) {
+                            Ok(()) => settings.save(),
+                            Err(message) => screen.message = Some(message),
+                        }
+                    }
+                    KeyCode::R => {
+                        settings.key_bindings = KeyBindings::default();
+                        settings.save();
+                    }
+                    KeyCode::Esc => close = true,
+                    _ => {}
                 }
             }
         }
+        if close {
+            state.key_bindings_screen = None;
+            break;
+        }
     }
 }
 
-fn inventory_commands(key: Key) -> Option<Command> {
-    use keys::KeyCode::*;
-    use item::Kind;
-
-    for kind in Kind::iter() {
-        let num_key = match inventory_key(kind) {
-            1 => D1,
-            2 => D2,
-            3 => D3,
-            4 => D4,
-            5 => D5,
-            6 => D6,
-            7 => D7,
-            8 => D8,
-            9 => D9,
-            _ => unreachable!("There should only ever be 9 item kinds at most."),
+/// Handle the keys pressed while the help screen is open. Like on
+/// the key bindings screen, these are fixed.
+fn process_help_screen(state: &mut State, settings: &Settings) {
+    let visible_lines = help::visible_lines(state.display_size);
+    let text_width = help::text_width(state.display_size);
+    while let Some(key) = state.keys.get() {
+        let mut screen = match state.help_screen {
+            Some(screen) => screen,
+            None => break,
         };
-
-        if key.code == num_key {
-            let command = match kind {
-                Kind::Food => Command::UseFood,
-                Kind::Dose => Command::UseDose,
-                Kind::CardinalDose => Command::UseCardinalDose,
-                Kind::DiagonalDose => Command::UseDiagonalDose,
-                Kind::StrongDose => Command::UseStrongDose,
-            };
-            return Some(command);
+        let line_count = help::lines(screen.page, state, &settings.key_bindings, text_width).len();
+        let max_scroll = line_count.saturating_sub(visible_lines);
+        let help_key = settings.key_bindings.action(key, state.replay) ==
+            Some(keymap::Action::Meta(MetaAction::Help));
+        if key.code == KeyCode::Esc || help_key {
+            state.help_screen = None;
+            break;
         }
+        match key.code {
+            KeyCode::Left => screen = HelpScreen { page: screen.page.previous(), scroll: 0 },
+            KeyCode::Right => screen = HelpScreen { page: screen.page.next(), scroll: 0 },
+            KeyCode::Up => screen.scroll = screen.scroll.saturating_sub(1),
+            KeyCode::Down => screen.scroll = cmp::min(screen.scroll + 1, max_scroll),
+            _ => {}
+        }
+        state.help_screen = Some(screen);
     }
-    None
 }
 
+/// The scroll position that shows the newest messages.
+fn message_log_max_scroll(state: &State) -> usize {
+    let visible_lines = help::visible_lines(state.display_size);
+    let text_width = help::text_width(state.display_size);
+    state.messages.lines(text_width).len().saturating_sub(visible_lines)
+}
+
+fn process_message_log_screen(state: &mut State, settings: &Settings) {
+    let max_scroll = message_log_max_scroll(state);
+    while let Some(key) = state.keys.get() {
+        let scroll = match state.message_log_screen {
+            Some(scroll) => scroll,
+            None => break,
+        };
+        let message_log_key = settings.key_bindings.action(key, state.replay) ==
+            Some(keymap::Action::Meta(MetaAction::MessageLog));
+        if key.code == KeyCode::Esc || message_log_key {
+            state.message_log_screen = None;
+            break;
+        }
+        state.message_log_screen = Some(match key.code {
+            KeyCode::Up => scroll.saturating_sub(1),
+            KeyCode::Down => cmp::min(scroll + 1, max_scroll),
+            _ => scroll,
+        });
+    }
+}
 
-pub fn inventory_key(kind: item::Kind) -> u8 {
-    use item::Kind::*;
-    match kind {
-        Food => 1,
-        Dose => 2,
-        CardinalDose => 3,
-        DiagonalDose => 4,
-        StrongDose => 5,
+/// Handle the keys pressed in the look mode: the movement keys move
+/// the cursor, Esc or the look key itself leave it.
+fn process_look_mode(state: &mut State, settings: &Settings) {
+    let display_area = Rectangle::center(state.screen_position_in_world, state.map_size / 2);
+    while let Some(key) = state.keys.get() {
+        // NOTE: the movement keys aren't active during a replay, but
+        // we want to move the cursor there too.
+        let action = settings.key_bindings.action(key, false);
+        if key.code == KeyCode::Esc || action == Some(keymap::Action::Meta(MetaAction::Look)) {
+            state.look_cursor = None;
+            break;
+        }
+        let offset = match action {
+            Some(keymap::Action::Game(GameAction::N)) => (0, -1),
+            Some(keymap::Action::Game(GameAction::S)) => (0, 1),
+            Some(keymap::Action::Game(GameAction::W)) => (-1, 0),
+            Some(keymap::Action::Game(GameAction::E)) => (1, 0),
+            Some(keymap::Action::Game(GameAction::NW)) => (-1, -1),
+            Some(keymap::Action::Game(GameAction::NE)) => (1, -1),
+            Some(keymap::Action::Game(GameAction::SW)) => (-1, 1),
+            Some(keymap::Action::Game(GameAction::SE)) => (1, 1),
+            _ => continue,
+        };
+        if let Some(cursor) = state.look_cursor {
+            let new_cursor = cursor + offset;
+            if display_area.contains(new_cursor) {
+                state.look_cursor = Some(new_cursor);
+            }
+        }
     }
 }
 
@@ -886,54 +1529,78 @@ fn kill_monster(monster_position: Point, world: &mut World) {
     }
 }
 
-fn use_dose(
+/// Apply the modifier to the player and log the changes they should
+/// know about.
+fn take_effect(
+    player: &mut player::Player,
+    modifier: player::Modifier,
+    messages: &mut MessageLog,
+    balance: &Balance,
+) {
+    let will = *player.will;
+    let stun = player.effects.turns_left(status::Kind::Stun);
+    let panic = player.effects.turns_left(status::Kind::Panic);
+    player.take_effect(modifier, balance);
+    if *player.will > will {
+        messages.add(Event::WillIncreased(*player.will));
+    } else if *player.will < will {
+        messages.add(Event::WillDecreased(*player.will));
+    }
+    let new_stun = player.effects.turns_left(status::Kind::Stun);
+    if new_stun > stun {
+        messages.add(Event::Stunned(new_stun));
+    }
+    let new_panic = player.effects.turns_left(status::Kind::Panic);
+    if new_panic > panic {
+        messages.add(Event::Panicked(new_panic));
+    }
+}
+
+fn use_item(
     player: &mut player::Player,
     explosion_animation: &mut Option<Box<AreaOfEffect>>,
     item: item::Item,
+    messages: &mut MessageLog,
+    balance: &Balance,
 ) {
-    use player::Modifier::*;
-    use item::Kind::*;
-    // TODO: do a different explosion animation for the cardinal dose
-    if let Intoxication { state_of_mind, .. } = item.modifier {
-        let radius = match state_of_mind <= 100 {
-            true => 4,
-            false => 6,
-        };
-        player.take_effect(item.modifier);
-        let animation: Box<AreaOfEffect> = match item.kind {
-            Dose | StrongDose => {
-                Box::new(animation::SquareExplosion::new(
-                    player.pos,
-                    radius,
-                    2,
-                    color::explosion,
-                ))
-            }
-            CardinalDose => {
-                Box::new(animation::CardinalExplosion::new(
-                    player.pos,
-                    radius,
-                    2,
-                    color::explosion,
-                    color::shattering_explosion,
-                ))
-            }
-            DiagonalDose => {
-                Box::new(animation::DiagonalExplosion::new(
-                    player.pos,
-                    radius,
-                    2,
-                    color::explosion,
-                    color::shattering_explosion,
-                ))
-            }
-            Food => unreachable!(),
-
-        };
-        *explosion_animation = Some(animation);
-    } else {
-        unreachable!();
-    }
+    use item::ExplosionShape::*;
+    messages.add(match item.modifier {
+        player::Modifier::Intoxication { .. } => Event::DoseTookEffect,
+        player::Modifier::Medication(turns) => Event::Medicated(turns),
+        player::Modifier::DoseBlocker(turns) => Event::DosesBlocked(turns),
+        _ => Event::AteFood,
+    });
+    take_effect(player, item.modifier, messages, balance);
+    let explosion = item.explosion;
+    let animation: Box<AreaOfEffect> = match explosion.shape {
+        Square => {
+            Box::new(animation::SquareExplosion::new(
+                player.pos,
+                explosion.radius,
+                explosion.initial_radius,
+                color::explosion,
+            ))
+        }
+        Cardinal => {
+            Box::new(animation::CardinalExplosion::new(
+                player.pos,
+                explosion.radius,
+                explosion.initial_radius,
+                color::explosion,
+                color::shattering_explosion,
+            ))
+        }
+        Diagonal => {
+            Box::new(animation::DiagonalExplosion::new(
+                player.pos,
+                explosion.radius,
+                explosion.initial_radius,
+                color::explosion,
+                color::shattering_explosion,
+            ))
+        }
+    };
+    *explosion_animation = Some(animation);
 }
 
 
@@ -985,23 +1652,23 @@ fn verify_states(expected: state::Verification, actual: state::Verification) {
         );
     }
     if expected.monsters != actual.monsters {
-        let expected_monsters: HashMap<Point, (Point, monster::Kind)> =
-            FromIterator::from_iter(expected.monsters.iter().map(|&(pos, chunk_pos, monster)| {
-                (pos, (chunk_pos, monster))
+        let expected_monsters: HashMap<MonsterId, (Point, Point, monster::Kind)> =
+            FromIterator::from_iter(expected.monsters.iter().map(|&(id, pos, chunk_pos, monster)| {
+                (id, (pos, chunk_pos, monster))
             }));
-        let actual_monsters: HashMap<Point, (Point, monster::Kind)> =
-            FromIterator::from_iter(actual.monsters.iter().map(|&(pos, chunk_pos, monster)| {
-                (pos, (chunk_pos, monster))
+        let actual_monsters: HashMap<MonsterId, (Point, Point, monster::Kind)> =
+            FromIterator::from_iter(actual.monsters.iter().map(|&(id, pos, chunk_pos, monster)| {
+                (id, (pos, chunk_pos, monster))
             }));
 
-        for (pos, expected) in &expected_monsters {
-            match actual_monsters.get(pos) {
+        for (id, expected) in &expected_monsters {
+            match actual_monsters.get(id) {
                 Some(actual) => {
                     if expected != actual {
                         println!(
-                            "Monster at {} differ. Expected: {:?}, \
+                            "Monster {:?} differs. Expected: {:?}, \
                                   actual: {:?}",
-                            pos,
+                            id,
                             expected,
                             actual
                         );
@@ -1009,20 +1676,257 @@ fn verify_states(expected: state::Verification, actual: state::Verification) {
                 }
                 None => {
                     println!(
-                        "Monster expected at {}: {:?}, but it's not \
+                        "Monster {:?} expected at {:?}, but it's not \
                               there.",
-                        pos,
+                        id,
                         expected
                     );
                 }
             }
         }
 
-        for (pos, actual) in &actual_monsters {
-            if expected_monsters.get(pos).is_none() {
-                println!("There is an unexpected monster at: {}: {:?}.", pos, actual);
+        for (id, actual) in &actual_monsters {
+            if expected_monsters.get(id).is_none() {
+                println!("There is an unexpected monster {:?}: {:?}.", id, actual);
             }
         }
     }
     assert!(expected == actual, "Validation failed!");
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::{process_monsters, process_offscreen_world};
+    use ai::AIState;
+    use formula::Balance;
+    use item;
+    use level::{Tile, TileKind};
+    use messages::MessageLog;
+    use monster::{self, MonsterId};
+    use player::Player;
+    use point::Point;
+    use rand::{IsaacRng, SeedableRng};
+    use rect::Rectangle;
+    use std::cmp;
+    use world::{self, World, TEST_ORIGIN};
+
+    const SEED: u32 = 42;
+    const CHUNK_SIZE: i32 = 32;
+
+    fn simulation_area() -> Rectangle {
+        Rectangle::center(Point::new(0, 0), Point::from_i32(20))
+    }
+
+    fn loaded_area() -> Rectangle {
+        Rectangle::center(Point::new(0, 0), Point::from_i32(80))
+    }
+
+    fn balance() -> Balance {
+        Balance {
+            offscreen_food_respawn_chance: 100,
+            ..Balance::default()
+        }
+    }
+
+    fn monsters(world: &World, area: Rectangle) -> Vec<(MonsterId, Point)> {
+        let mut result: Vec<_> = world.monsters(area).map(|m| (m.id, m.position)).collect();
+        result.sort_by_key(|&(id, _)| id);
+        result
+    }
+
+    fn items(world: &World, area: Rectangle) -> Vec<(Point, Vec<item::Kind>)> {
+        area.points()
+            .filter_map(|pos| {
+                world.cell(pos).map(|cell| (pos, cell.items.iter().map(|item| item.kind).collect()))
+            })
+            .collect()
+    }
+
+    fn coarse_steps(world: &mut World, turns: i32, balance: &Balance) {
+        let player = Player::new(Point::new(0, 0), false, balance);
+        for turn in 1..(turns + 1) {
+            process_offscreen_world(
+                world,
+                &player,
+                simulation_area(),
+                loaded_area(),
+                CHUNK_SIZE,
+                SEED,
+                turn,
+                balance,
+            );
+        }
+    }
+
+    #[test]
+    fn coarse_simulation_is_deterministic() {
+        let balance = balance();
+        let mut first = world::test_world(SEED, loaded_area());
+        let mut second = world::test_world(SEED, loaded_area());
+        coarse_steps(&mut first, 10, &balance);
+        coarse_steps(&mut second, 10, &balance);
+        assert_eq!(monsters(&first, loaded_area()), monsters(&second, loaded_area()));
+        assert_eq!(items(&first, loaded_area()), items(&second, loaded_area()));
+    }
+
+    #[test]
+    fn coarse_simulation_leaves_the_simulation_area_alone() {
+        let balance = balance();
+        let mut world = world::test_world(SEED, loaded_area());
+        let monsters_before = monsters(&world, simulation_area());
+        let items_before = items(&world, simulation_area());
+        coarse_steps(&mut world, 10, &balance);
+        // NOTE: monsters from the outside can wander in, but the
+        // ones inside must stay put.
+        for (id, pos) in monsters_before {
+            assert_eq!(world.monster(id).map(|m| m.position), Some(pos));
+        }
+        assert_eq!(items(&world, simulation_area()), items_before);
+    }
+
+    #[test]
+    fn coarse_simulation_leaves_the_chase_alone() {
+        let balance = balance();
+        let mut world = world::test_world(SEED, loaded_area());
+        let (id, pos) = *monsters(&world, loaded_area())
+            .iter()
+            .find(|&&(_, pos)| !simulation_area().contains(pos))
+            .expect("There should be monsters outside of the simulation area.");
+        if let Some(monster) = world.monster_mut(id) {
+            monster.ai_state = AIState::Chasing;
+        }
+        coarse_steps(&mut world, 10, &balance);
+        let monster = world.monster(id).unwrap();
+        assert_eq!(monster.ai_state, AIState::Chasing);
+        assert_eq!(monster.position, pos);
+    }
+
+    /// The food in every chunk outside of the simulation area along
+    /// with the most the coarse simulation is allowed to put there.
+    fn food_counts(world: &World) -> Vec<(Point, u32, u32)> {
+        let archetypes = world.item_archetypes();
+        let food = archetypes.food();
+        let mut result = vec![];
+        for chunk_position in world.positions_of_all_chunks() {
+            let chunk_area =
+                Rectangle::from_point_and_size(chunk_position, Point::from_i32(CHUNK_SIZE));
+            if chunk_area.intersects(simulation_area()) {
+                continue;
+            }
+            let mut empty_tiles = 0;
+            let mut food_count = 0;
+            for pos in chunk_area.points() {
+                if let Some(cell) = world.cell(pos) {
+                    if cell.tile.kind == TileKind::Empty {
+                        empty_tiles += 1;
+                    }
+                    food_count += cell.items.iter().filter(|item| item.kind == food).count() as u32;
+                }
+            }
+            let cap = archetypes.expected_count(food, empty_tiles);
+            result.push((chunk_position, food_count, cap));
+        }
+        result.sort_by_key(|&(pos, _, _)| (pos.x, pos.y));
+        result
+    }
+
+    #[test]
+    fn offscreen_food_is_capped() {
+        let balance = balance();
+        let mut world = world::test_world(SEED, loaded_area());
+        let before = food_counts(&world);
+        coarse_steps(&mut world, 200, &balance);
+        let after = food_counts(&world);
+        assert_eq!(before.len(), after.len());
+        for (&(pos, generated, cap), &(_, food_count, _)) in before.iter().zip(after.iter()) {
+            // NOTE: the generator itself can go over the average
+            assert!(
+                food_count <= cmp::max(generated, cap),
+                "Chunk {:?} has {} pieces of food, expected at most {}.",
+                pos,
+                food_count,
+                cmp::max(generated, cap)
+            );
+        }
+    }
+
+    /// How far from the player the `forest_with_anxiety` goes. The
+    /// monster turns only happen within it so the rest of the world
+    /// doesn't get in the way.
+    const FOREST_RADIUS: i32 = 10;
+
+    /// A forest with a clearing for the player at `TEST_ORIGIN` and an
+    /// Anxiety `distance` tiles to the right of them. There's a
+    /// tree right next to the Anxiety and the path between it and
+    /// the player is clear otherwise.
+    fn forest_with_anxiety(distance: i32) -> (World, Player, MonsterId) {
+        let balance = Balance::default();
+        assert!(distance < FOREST_RADIUS);
+        let area = Rectangle::center(TEST_ORIGIN, Point::from_i32(FOREST_RADIUS));
+        let mut world = world::test_world_of_trees(area);
+        for x in 0..(distance + 1) {
+            if x != distance - 1 {
+                if let Some(cell) = world.cell_mut(TEST_ORIGIN + (x, 0)) {
+                    cell.tile = Tile::new(TileKind::Empty);
+                }
+            }
+        }
+        let player = Player::new(TEST_ORIGIN, false, &balance);
+        let id = world
+            .spawn_monster(monster::Kind::Anxiety, TEST_ORIGIN + (distance, 0), TEST_ORIGIN)
+            .expect("The Anxiety should fit in the clearing.");
+        (world, player, id)
+    }
+
+    fn monster_turn(world: &mut World, player: &mut Player, rng: &mut IsaacRng) {
+        let balance = Balance::default();
+        let area = Rectangle::center(player.pos, Point::from_i32(FOREST_RADIUS));
+        process_monsters(world, player, area, area, rng, &mut MessageLog::new(), &balance);
+    }
+
+    fn trees(world: &World, area: Rectangle) -> Vec<Point> {
+        area.points()
+            .filter(|&pos| world.cell(pos).map_or(false, |cell| cell.tile.kind == TileKind::Tree))
+            .collect()
+    }
+
+    #[test]
+    fn chasing_monsters_cut_the_tree_and_step_in_next_turn() {
+        let rng_seed: &[_] = &[SEED];
+        let mut rng: IsaacRng = SeedableRng::from_seed(rng_seed);
+        let (mut world, mut player, id) = forest_with_anxiety(4);
+        let start = world.monster(id).unwrap().position;
+
+        monster_turn(&mut world, &mut player, &mut rng);
+        let (position, cut) = {
+            let monster = world.monster(id).unwrap();
+            assert_eq!(monster.ai_state, AIState::Chasing);
+            (monster.position, monster.path[0])
+        };
+        assert_eq!(position, start);
+        assert_eq!(cut.tile_distance(start), 1);
+        assert_eq!(world.cell(cut).unwrap().tile.kind, TileKind::Empty);
+
+        monster_turn(&mut world, &mut player, &mut rng);
+        assert_eq!(world.monster(id).unwrap().position, cut);
+    }
+
+    #[test]
+    fn idle_monsters_leave_the_trees_alone() {
+        let rng_seed: &[_] = &[SEED];
+        let mut rng: IsaacRng = SeedableRng::from_seed(rng_seed);
+        let (mut world, mut player, id) = forest_with_anxiety(7);
+        let start = world.monster(id).unwrap().position;
+        let area = Rectangle::center(start, Point::from_i32(3));
+        let trees_before = trees(&world, area);
+
+        for _ in 0..10 {
+            monster_turn(&mut world, &mut player, &mut rng);
+        }
+        let monster = world.monster(id).unwrap();
+        assert_eq!(monster.ai_state, AIState::Idle);
+        assert_eq!(monster.position, start);
+        assert_eq!(trees(&world, area), trees_before);
+    }
+}
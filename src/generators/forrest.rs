@@ -1,19 +1,25 @@
 use generators::GeneratedWorld;
 
 use color;
-use formula;
-use item::{self, Item};
+use data::{ItemArchetypes, MonsterArchetypes};
+use item::{self, Equipment, Item};
 use level::{Tile, TileKind};
-use monster::{Kind, Monster};
+use monster::{Kind, Monster, MonsterId};
 use player::Modifier;
 use point::Point;
 use rand::Rng;
 use rand::distributions::{IndependentSample, Weighted, WeightedChoice};
+use rect::Rectangle;
 
-// TODO: Instead of `map_size`, use a Rectangle with the world
-// positions here. We want to expose the non-world coordinates in as
-// few places as possible.
-fn generate_map<R: Rng, G: Rng>(rng: &mut R, one_off_rng: &mut G, map_size: Point, player_pos: Point) -> Vec<(Point, Tile)> {
+
+/// On average, one in this many empty tiles gets a piece of
+/// equipment.
+const EQUIPMENT_RARITY: u32 = 2000;
+
+/// Generate the tiles for the given `area`. Both the `area` and
+/// `player_pos` are in world coordinates and so are the returned
+/// positions.
+fn generate_map<R: Rng, G: Rng>(rng: &mut R, one_off_rng: &mut G, area: Rectangle, player_pos: Point) -> Vec<(Point, Tile)> {
     let mut weights = [
         Weighted {
             weight: 610,
@@ -26,74 +32,56 @@ fn generate_map<R: Rng, G: Rng>(rng: &mut R, one_off_rng: &mut G, map_size: Poin
     ];
     let opts = WeightedChoice::new(&mut weights);
     let mut result = vec![];
-    // NOTE: starting with `y` seems weird but it'll generate the right pattern:
-    // start at top left corner, moving to the right
-    for y in 0..map_size.y {
-        for x in 0..map_size.x {
-            // TODO: due to coordinate conversion, this is wrong for
-            // every chunk but the one the player is in.
-            //
-            // Player always starts at an empty space:
-            let kind = match player_pos == (x, y) {
-                true => TileKind::Empty,
-                false => opts.ind_sample(rng),
-            };
-
-            let mut tile = Tile::new(kind);
-            if tile.kind == TileKind::Tree {
-                let options = [color::tree_1, color::tree_2, color::tree_3];
-                tile.fg_color = *one_off_rng.choose(&options).unwrap();
-            }
+    // NOTE: `Rectangle::points` starts at the top left corner and
+    // moves to the right, row by row.
+    for pos in area.points() {
+        // Player always starts at an empty space:
+        let kind = match player_pos == pos {
+            true => TileKind::Empty,
+            false => opts.ind_sample(rng),
+        };
 
-            result.push((Point::new(x, y), tile));
+        let mut tile = Tile::new(kind);
+        if tile.kind == TileKind::Tree {
+            let options = [color::tree_1, color::tree_2, color::tree_3];
+            tile.fg_color = *one_off_rng.choose(&options).unwrap();
         }
+
+        result.push((pos, tile));
     }
     result
 }
 
-fn generate_monsters<R: Rng>(rng: &mut R, map: &[(Point, Tile)]) -> Vec<Monster> {
-    // 3% chance a monster gets spawned
-    let monster_count = 5;
-    let monster_chance = 30;
-    let mut weights = [
+fn generate_monsters<R: Rng>(
+    rng: &mut R,
+    monster_archetypes: &MonsterArchetypes,
+    area: Rectangle,
+    map: &[(Point, Tile)],
+) -> Vec<Monster> {
+    // NOTE: the item is the index into `monster_archetypes.archetypes`
+    let mut weights = vec![
         Weighted {
-            weight: 1000 - monster_chance,
+            weight: monster_archetypes.no_monster_weight,
             item: None,
         },
-        Weighted {
-            weight: monster_chance / monster_count,
-            item: Some(Kind::Anxiety),
-        },
-        Weighted {
-            weight: monster_chance / monster_count,
-            item: Some(Kind::Depression),
-        },
-        Weighted {
-            weight: monster_chance / monster_count,
-            item: Some(Kind::Hunger),
-        },
-        Weighted {
-            weight: monster_chance / monster_count,
-            item: Some(Kind::Shadows),
-        },
-        Weighted {
-            weight: monster_chance / monster_count,
-            item: Some(Kind::Voices),
-        },
-        Weighted {
-            weight: 10,
-            item: Some(Kind::Npc),
-        },
     ];
+    for (index, archetype) in monster_archetypes.archetypes.iter().enumerate() {
+        weights.push(Weighted {
+            weight: archetype.spawn_weight,
+            item: Some(index),
+        });
+    }
     let opts = WeightedChoice::new(&mut weights);
     let mut result = vec![];
     for &(pos, tile) in map.iter() {
         if tile.kind != TileKind::Empty {
             continue;
         }
-        if let Some(kind) = opts.ind_sample(rng) {
-            let mut monster = Monster::new(kind, pos);
-            match kind {
+        if let Some(index) = opts.ind_sample(rng) {
+            let archetype = &monster_archetypes.archetypes[index];
+            let id = MonsterId::new(area.top_left(), result.len());
+            let mut monster = Monster::new(id, archetype, pos);
+            match archetype.kind {
                 Kind::Npc => {
                     use monster::CompanionBonus::*;
                     use color;
@@ -113,83 +101,41 @@ fn generate_monsters<R: Rng>(rng: &mut R, map: &[(Point, Tile)]) -> Vec<Monster>
     result
 }
 
-fn new_item<R: Rng>(kind: item::Kind, rng: &mut R) -> Item {
-    use item::Kind::*;
-    match kind {
-        Dose => {
-            let mut item = formula::DOSE_PREFAB;
-            match item.modifier {
-                Modifier::Intoxication{ref mut state_of_mind, ..} => {
-                    *state_of_mind += formula::DOSE_MIND_VARIANCE.random(rng);
-                }
-                _ => {},
-            };
-            item
-        }
-        StrongDose => {
-            let mut item = formula::STRONG_DOSE_PREFAB;
-            match item.modifier {
-                Modifier::Intoxication{ref mut state_of_mind, ..} => {
-                    *state_of_mind += formula::STRONG_DOSE_MIND_VARIANCE.random(rng);
-                }
-                _ => {},
-            };
-            item
-        }
-        CardinalDose => {
-            let mut item = formula::CARDINAL_DOSE_PREFAB;
-            match item.modifier {
-                Modifier::Intoxication{ref mut state_of_mind, ..} => {
-                    *state_of_mind += formula::CARDINAL_DOSE_MIND_VARIANCE.random(rng);
-                }
-                _ => {},
-            };
-            item
-        }
-        DiagonalDose => {
-            let mut item = formula::DIAGONAL_DOSE_PREFAB;
-            match item.modifier {
-                Modifier::Intoxication{ref mut state_of_mind, ..} => {
-                    *state_of_mind += formula::DIAGONAL_DOSE_MIND_VARIANCE.random(rng);
-                }
-                _ => {},
-            };
-            item
+fn new_item<R: Rng>(item_archetypes: &ItemArchetypes, kind: item::Kind, rng: &mut R) -> Item {
+    let mut item = item_archetypes.item(kind);
+    // NOTE: This how much a given item can vary from the archetype's
+    // base value
+    if let Some(variance) = item_archetypes.archetype(kind).mind_variance {
+        match item.modifier {
+            Modifier::Intoxication { ref mut state_of_mind, .. } |
+            Modifier::Attribute { ref mut state_of_mind, .. } => {
+                *state_of_mind += variance.random(rng);
+            }
+            _ => {}
         }
-        Food => formula::FOOD_PREFAB,
     }
+    item
 }
 
 
-fn generate_items<R: Rng>(rng: &mut R, map: &[(Point, Tile)]) -> Vec<(Point, Item)> {
-    use item::Kind::*;
-
-    let mut weights = [
+fn generate_items<R: Rng>(
+    rng: &mut R,
+    item_archetypes: &ItemArchetypes,
+    map: &[(Point, Tile)],
+) -> Vec<(Point, Item)> {
+    // NOTE: the item is the archetype's `item::Kind`
+    let mut weights = vec![
         Weighted {
-            weight: 1000,
+            weight: item_archetypes.no_item_weight,
             item: None,
         },
-        Weighted {
-            weight: 8,
-            item: Some(Dose),
-        },
-        Weighted {
-            weight: 3,
-            item: Some(StrongDose),
-        },
-        Weighted {
-            weight: 2,
-            item: Some(CardinalDose),
-        },
-        Weighted {
-            weight: 2,
-            item: Some(DiagonalDose),
-        },
-        Weighted {
-            weight: 5,
-            item: Some(Food),
-        },
     ];
+    for kind in item_archetypes.kinds() {
+        weights.push(Weighted {
+            weight: item_archetypes.archetype(kind).spawn_weight,
+            item: Some(kind),
+        });
+    }
 
     let generator = WeightedChoice::new(&mut weights);
 
@@ -201,7 +147,7 @@ fn generate_items<R: Rng>(rng: &mut R, map: &[(Point, Tile)]) -> Vec<(Point, Ite
             }
             TileKind::Empty => {
                 if let Some(kind) = generator.ind_sample(rng) {
-                    result.push((pos, new_item(kind, rng)));
+                    result.push((pos, new_item(item_archetypes, kind, rng)));
                 }
             }
         }
@@ -210,9 +156,45 @@ fn generate_items<R: Rng>(rng: &mut R, map: &[(Point, Tile)]) -> Vec<(Point, Ite
 }
 
 
-pub fn generate<R: Rng, G: Rng>(rng: &mut R, one_off_rng: &mut G, size: Point, player: Point) -> GeneratedWorld {
-    let map = generate_map(rng, one_off_rng, size, player);
-    let monsters = generate_monsters(rng, &map);
-    let items = generate_items(rng, &map);
-    (map, monsters, items)
+fn generate_equipment<R: Rng>(
+    rng: &mut R,
+    map: &[(Point, Tile)],
+    items: &[(Point, Item)],
+) -> Vec<(Point, Equipment)> {
+    let mut result = vec![];
+    for &(pos, tile) in map.iter() {
+        if tile.kind != TileKind::Empty {
+            continue;
+        }
+        if rng.gen_range(0, EQUIPMENT_RARITY) == 0 {
+            // NOTE: keep the equipment on its own tile so it's not
+            // hidden under an item
+            let has_item = items.iter().any(|&(item_pos, _)| item_pos == pos);
+            if !has_item {
+                let equipment = *rng.choose(&Equipment::all()).unwrap();
+                result.push((pos, equipment));
+            }
+        }
+    }
+    result
+}
+
+
+/// Generate the contents of the world `area`.
+///
+/// All the positions (the `area`, `player` as well as everything in
+/// the result) are in world coordinates.
+pub fn generate<R: Rng, G: Rng>(
+    rng: &mut R,
+    one_off_rng: &mut G,
+    monster_archetypes: &MonsterArchetypes,
+    item_archetypes: &ItemArchetypes,
+    area: Rectangle,
+    player: Point,
+) -> GeneratedWorld {
+    let map = generate_map(rng, one_off_rng, area, player);
+    let monsters = generate_monsters(rng, monster_archetypes, area, &map);
+    let items = generate_items(rng, item_archetypes, &map);
+    let equipment = generate_equipment(rng, &map, &items);
+    (map, monsters, items, equipment)
 }
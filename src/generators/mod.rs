@@ -1,14 +1,27 @@
-use item::Item;
+use item::{Equipment, Item};
 use level::Tile;
 use monster::Monster;
 use point::Point;
 
 
 pub mod forrest;
+pub mod tutorial;
 
 
+/// Which generator fills the new chunks of the world.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Generator {
+    Forrest,
+    /// A small hand-authored map surrounded by trees.
+    Tutorial,
+}
+
+
+/// The tiles, monsters, items and equipment of a generated area. All
+/// positions are in world coordinates.
 pub type GeneratedWorld = (
     Vec<(Point, Tile)>,
     Vec<Monster>,
     Vec<(Point, Item)>,
+    Vec<(Point, Equipment)>,
 );
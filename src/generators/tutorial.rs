@@ -0,0 +1,117 @@
+use generators::GeneratedWorld;
+
+use color;
+use data::ItemArchetypes;
+use level::{Tile, TileKind};
+use point::Point;
+use rect::Rectangle;
+
+
+/// The tutorial map. `@` is where the player starts, `#` is a tree,
+/// `%` is food, `i` a dose and `.` empty ground.
+///
+/// There are no monsters here, the tutorial spawns them when the
+/// player gets to them.
+const MAP: &[&str] = &[
+    "##################################",
+    "#.....#.........#................#",
+    "#.....#....%....#................#",
+    "#..@.......................i.....#",
+    "#.....#.........#................#",
+    "#.%...#.........#.......%........#",
+    "###.###.........#####.############",
+    "#.....#..........................#",
+    "#.....#......%...................#",
+    "##################################",
+];
+
+
+/// Position of the `@` in the `MAP`.
+fn start_position() -> Point {
+    for (y, row) in MAP.iter().enumerate() {
+        if let Some(x) = row.find('@') {
+            return Point::new(x as i32, y as i32);
+        }
+    }
+    panic!("The tutorial map doesn't have the player's starting position.");
+}
+
+
+/// The `MAP` character at the given world position. Everything
+/// outside of the map is a tree.
+fn map_char(pos: Point, player: Point) -> char {
+    let map_pos = pos - player + start_position();
+    if map_pos.x < 0 || map_pos.y < 0 {
+        return '#';
+    }
+    MAP.get(map_pos.y as usize)
+        .and_then(|row| row.chars().nth(map_pos.x as usize))
+        .unwrap_or('#')
+}
+
+
+/// Generate the contents of the world `area` from the tutorial map.
+/// The map is placed so the player's starting position lines up
+/// with `player`.
+///
+/// All the positions (the `area`, `player` as well as everything in
+/// the result) are in world coordinates.
+pub fn generate(item_archetypes: &ItemArchetypes, area: Rectangle, player: Point) -> GeneratedWorld {
+    let mut map = vec![];
+    let mut items = vec![];
+    for pos in area.points() {
+        let glyph = map_char(pos, player);
+        let kind = match glyph {
+            '#' => TileKind::Tree,
+            _ => TileKind::Empty,
+        };
+        let mut tile = Tile::new(kind);
+        if kind == TileKind::Tree {
+            // NOTE: vary the trees the same way every time
+            let options = [color::tree_1, color::tree_2, color::tree_3];
+            tile.fg_color = options[((pos.x + pos.y) % 3).abs() as usize];
+        }
+        map.push((pos, tile));
+
+        match glyph {
+            '%' => items.push((pos, item_archetypes.item(item_archetypes.food()))),
+            'i' => {
+                let mut dose = item_archetypes.item(item_archetypes.starting_dose());
+                // NOTE: the player should pick this one up, not be
+                // pulled in and use it straight away
+                dose.irresistible = 0;
+                items.push((pos, dose));
+            }
+            _ => {}
+        }
+    }
+    (map, vec![], items, vec![])
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{map_char, start_position, MAP};
+    use point::Point;
+
+    #[test]
+    fn map_rows_are_equally_long() {
+        for row in MAP {
+            assert_eq!(row.len(), MAP[0].len());
+        }
+    }
+
+    #[test]
+    fn player_starts_at_the_at_sign() {
+        let player = Point::new(0, 0);
+        assert_eq!(map_char(player, player), '@');
+        assert_eq!(start_position(), Point::new(3, 3));
+    }
+
+    #[test]
+    fn outside_of_the_map_are_trees() {
+        let player = Point::new(10, 10);
+        assert_eq!(map_char(Point::new(0, 0), player), '#');
+        assert_eq!(map_char(Point::new(1000, 10), player), '#');
+    }
+}
@@ -0,0 +1,190 @@
+use color::{self, Color};
+use keymap::{self, Action, KeyBindings, MetaAction};
+use item::Equipment;
+use look;
+use monster;
+use player::Modifier;
+use point::Point;
+use state::State;
+use std::cmp;
+use tips::Tip;
+use util;
+
+
+/// The pages of the help screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Page {
+    Controls,
+    Legend,
+    Tips,
+}
+
+impl Page {
+    pub fn next(self) -> Self {
+        match self {
+            Page::Controls => Page::Legend,
+            Page::Legend => Page::Tips,
+            Page::Tips => Page::Controls,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            Page::Controls => Page::Tips,
+            Page::Legend => Page::Controls,
+            Page::Tips => Page::Legend,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Page::Controls => "Controls",
+            Page::Legend => "Legend",
+            Page::Tips => "Tips",
+        }
+    }
+}
+
+
+/// The state of the help screen while it's open.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HelpScreen {
+    pub page: Page,
+    /// The index of the first line shown on the page.
+    pub scroll: usize,
+}
+
+impl Default for HelpScreen {
+    fn default() -> Self {
+        HelpScreen {
+            page: Page::Controls,
+            scroll: 0,
+        }
+    }
+}
+
+
+/// The space between the help text and the edges of the screen.
+pub const PADDING: i32 = 2;
+
+/// How many lines of a page fit on the screen. The rest has to be
+/// scrolled to.
+pub fn visible_lines(display_size: Point) -> usize {
+    // NOTE: the title and the footer, each with an empty line
+    // between them and the page
+    cmp::max(1, display_size.y - 5) as usize
+}
+
+/// The width the page's text is wrapped to.
+pub fn text_width(display_size: Point) -> usize {
+    cmp::max(10, display_size.x - 2 * PADDING) as usize
+}
+
+
+pub enum Line {
+    Empty,
+    Text(String),
+    /// An action and the keys bound to it.
+    Binding(String, String),
+    /// A glyph in its colour and what it stands for.
+    Glyph(char, Color, String),
+}
+
+
+/// The tips on the help page. The ones the endgame screen shows as
+/// well come from `Tip` so they stay the same in both places.
+fn basic_tips() -> Vec<&'static str> {
+    vec![
+        "Doses get you High. That keeps the withdrawal away, destroys the monsters nearby and lets you see further.",
+        "You can't stay High forever. When it wears off, you go into a withdrawal and if that goes on for too long, you die of exhaustion.",
+        Tip::CarryFood.text(),
+        "At first, don't pick up doses unless you're Sober. Using another dose while High will likely make you overdose.",
+        Tip::RiskyDosesBlink.text(),
+        "Your tolerance fades while you stay off the doses. After a long break, a dose hits much harder than you remember.",
+        Tip::DrawnToDose.text(),
+        "Medication (`!`) keeps the withdrawal from getting worse for a while. A blocker (`=`) stops the doses from pulling you in.",
+        "Staying Sober at maximum Will earns you sobriety tokens. Each one helps you resist the doses for the rest of the game.",
+        "People can accompany you and help you out, but they won't talk to you while you're High.",
+        "Get your Will to the maximum and stay Sober for a while to win.",
+    ]
+}
+
+
+/// The contents of the help page. The text is wrapped to `width`.
+pub fn lines(page: Page, state: &State, key_bindings: &KeyBindings, width: usize) -> Vec<Line> {
+    match page {
+        Page::Controls => controls(state, key_bindings),
+        Page::Legend => legend(state),
+        Page::Tips => {
+            let mut lines = vec![];
+            for tip in basic_tips() {
+                lines.extend(util::wrap_text(tip, width).into_iter().map(Line::Text));
+                lines.push(Line::Empty);
+            }
+            lines
+        }
+    }
+}
+
+
+fn controls(state: &State, key_bindings: &KeyBindings) -> Vec<Line> {
+    Action::all()
+        .into_iter()
+        .filter(|&action| match action {
+            // NOTE: only show the cheats to the people who know about them
+            Action::Meta(MetaAction::ToggleCheats) => false,
+            Action::Meta(MetaAction::CheatInvincible) |
+            Action::Meta(MetaAction::CheatFood) => state.cheating,
+            Action::Meta(MetaAction::PauseReplay) |
+            Action::Meta(MetaAction::StepReplay) => state.replay,
+            _ => true,
+        })
+        .map(|action| {
+            let keys = key_bindings
+                .keys(action)
+                .into_iter()
+                .map(keymap::key_name)
+                .collect::<Vec<_>>()
+                .join(", ");
+            Line::Binding(action.description(), keys)
+        })
+        .collect()
+}
+
+
+fn legend(state: &State) -> Vec<Line> {
+    let mut lines = vec![
+        Line::Glyph('@', color::player, "You".into()),
+        Line::Glyph('.', color::empty_tile, "Empty ground".into()),
+        Line::Glyph('#', color::tree_1, "Tree, it's in the way until someone cuts it down".into()),
+        Line::Empty,
+    ];
+
+    for archetype in &state.world.monster_archetypes().archetypes {
+        let description = if archetype.kind == monster::Kind::Npc {
+            format!("{}: can accompany you", archetype.name)
+        } else {
+            format!("{}: {}", archetype.name, look::describe_modifier(archetype.attack))
+        };
+        lines.push(Line::Glyph(archetype.glyph, archetype.color, description));
+    }
+    lines.push(Line::Empty);
+
+    for archetype in &state.world.item_archetypes().archetypes {
+        let description = match archetype.modifier {
+            Modifier::Intoxication { state_of_mind, .. } => {
+                format!("{}: a dose of strength {}", archetype.name, state_of_mind)
+            }
+            modifier => format!("{}: {}", archetype.name, look::describe_modifier(modifier)),
+        };
+        lines.push(Line::Glyph(archetype.glyph, archetype.color, description));
+    }
+    lines.push(Line::Empty);
+
+    for &equipment in Equipment::all().iter() {
+        let description = format!("{}: {}", equipment.name(), equipment.description());
+        lines.push(Line::Glyph(equipment.glyph(), equipment.color(), description));
+    }
+
+    lines
+}
@@ -1,62 +1,100 @@
-
-
-use self::Kind::*;
-
 use color::{self, Color};
 use graphics::Render;
 use player::Modifier;
 use std::time::Duration;
 
 
-#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash)]
-pub enum Kind {
-    Food,
-    Dose,
-    StrongDose,
-    CardinalDose,
-    DiagonalDose,
-}
+/// Identifies the archetype the item was created from. It's the
+/// archetype's position in `data::ItemArchetypes`.
+#[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Kind(pub usize);
 
-impl Kind {
-    pub fn iter() -> KindIterator {
-        KindIterator { current: Some(self::Kind::Food) }
-    }
-}
 
-pub struct KindIterator {
-    current: Option<Kind>,
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ExplosionShape {
+    Square,
+    Cardinal,
+    Diagonal,
 }
 
-impl Iterator for KindIterator {
-    type Item = Kind;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        use self::Kind::*;
-        let current = self.current;
-        self.current = match current {
-            Some(Food) => Some(Dose),
-            Some(Dose) => Some(StrongDose),
-            Some(StrongDose) => Some(CardinalDose),
-            Some(CardinalDose) => Some(DiagonalDose),
-            Some(DiagonalDose) => None,
-            None => None,
-        };
-        current
-    }
+/// The area of effect of using an item.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Explosion {
+    pub shape: ExplosionShape,
+    pub radius: i32,
+    pub initial_radius: i32,
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Item {
     pub kind: Kind,
+    pub glyph: char,
+    pub color: Color,
     pub modifier: Modifier,
     pub irresistible: i32,
+    pub explosion: Explosion,
 }
 
 impl Item {
     pub fn is_dose(&self) -> bool {
-        match self.kind {
-            Dose | StrongDose | CardinalDose | DiagonalDose => true,
-            Food => false,
+        match self.modifier {
+            Modifier::Intoxication { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+
+/// How far the gun shoots.
+pub const GUN_RANGE: i32 = 4;
+
+
+/// Tools the player keeps once they pick them up. Unlike the items
+/// in the inventory, they're never used up and can't be dropped.
+/// Instead, they change what the player's actions do.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Equipment {
+    /// Bumping into a tree destroys it.
+    Pickaxe,
+    /// Moving towards a monster within `GUN_RANGE` shoots everything
+    /// in that direction instead.
+    Gun,
+}
+
+impl Equipment {
+    pub fn all() -> [Equipment; 2] {
+        [Equipment::Pickaxe, Equipment::Gun]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Equipment::Pickaxe => "Pickaxe",
+            Equipment::Gun => "Gun",
+        }
+    }
+
+    pub fn glyph(self) -> char {
+        match self {
+            Equipment::Pickaxe => '(',
+            Equipment::Gun => '}',
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Equipment::Pickaxe => color::pickaxe,
+            Equipment::Gun => color::gun,
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Equipment::Pickaxe => "walk into a tree to cut it down".into(),
+            Equipment::Gun => format!(
+                "walk towards a monster up to {} tiles away to shoot it",
+                GUN_RANGE
+            ),
         }
     }
 }
@@ -64,12 +102,12 @@ impl Item {
 
 impl Render for Item {
     fn render(&self, _dt: Duration) -> (char, Color, Option<Color>) {
-        match self.kind {
-            Food => ('%', color::food, None),
-            Dose => ('i', color::dose, None),
-            StrongDose => ('I', color::dose_glow, None),
-            CardinalDose => ('+', color::shattering_dose, None),
-            DiagonalDose => ('x', color::shattering_dose, None),
-        }
+        (self.glyph, self.color, None)
+    }
+}
+
+impl Render for Equipment {
+    fn render(&self, _dt: Duration) -> (char, Color, Option<Color>) {
+        (self.glyph(), self.color(), None)
     }
 }
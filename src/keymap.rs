@@ -0,0 +1,500 @@
+use keys::{Key, KeyCode};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+
+/// Things the player does in the game world. They end up as
+/// `Command`s recorded in the replay.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameAction {
+    N,
+    E,
+    S,
+    W,
+    NE,
+    NW,
+    SE,
+    SW,
+    Eat,
+    /// Use the item with this `inventory_key` (1-9).
+    UseInventory(u8),
+    /// Keep walking to the nearest unexplored place.
+    Explore,
+    /// Walk to the nearest visible food.
+    TravelToFood,
+    /// Walk to the nearest visible dose.
+    TravelToDose,
+    /// Walk to the nearest visible NPC that can accompany the player.
+    TravelToCompanion,
+    /// Pass one turn.
+    Wait,
+    /// Keep passing turns until something happens.
+    Rest,
+}
+
+
+/// Everything that's not part of the game itself: restarting,
+/// quitting, changing the settings, cheats, etc.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MetaAction {
+    Quit,
+    NewGame,
+    ToggleFullscreen,
+    CycleColorTheme,
+    KeyBindings,
+    /// Move a cursor over the map and describe what's under it.
+    Look,
+    MessageLog,
+    Help,
+    ToggleCheats,
+    CheatInvincible,
+    CheatFood,
+    PauseReplay,
+    StepReplay,
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Game(GameAction),
+    Meta(MetaAction),
+}
+
+impl Action {
+    /// All the actions that can be bound, in the order they're
+    /// listed on the key bindings screen.
+    pub fn all() -> Vec<Action> {
+        use self::Action::*;
+        use self::GameAction::*;
+        use self::MetaAction::*;
+        let mut result = vec![
+            Game(N), Game(S), Game(W), Game(E),
+            Game(NW), Game(NE), Game(SW), Game(SE),
+            Game(Eat),
+        ];
+        result.extend((1..10).map(|slot| Game(UseInventory(slot))));
+        result.extend(&[
+            Game(Explore),
+            Game(TravelToFood),
+            Game(TravelToDose),
+            Game(TravelToCompanion),
+            Game(Wait),
+            Game(Rest),
+        ]);
+        result.extend(&[
+            Meta(Quit),
+            Meta(NewGame),
+            Meta(ToggleFullscreen),
+            Meta(CycleColorTheme),
+            Meta(KeyBindings),
+            Meta(Look),
+            Meta(MessageLog),
+            Meta(Help),
+            Meta(ToggleCheats),
+            Meta(CheatInvincible),
+            Meta(CheatFood),
+            Meta(PauseReplay),
+            Meta(StepReplay),
+        ]);
+        result
+    }
+
+    pub fn description(self) -> String {
+        use self::Action::*;
+        use self::GameAction::*;
+        use self::MetaAction::*;
+        match self {
+            Game(N) => "Move north".into(),
+            Game(S) => "Move south".into(),
+            Game(W) => "Move west".into(),
+            Game(E) => "Move east".into(),
+            Game(NW) => "Move north-west".into(),
+            Game(NE) => "Move north-east".into(),
+            Game(SW) => "Move south-west".into(),
+            Game(SE) => "Move south-east".into(),
+            Game(Eat) => "Eat food".into(),
+            Game(UseInventory(slot)) => format!("Use inventory item {}", slot),
+            Game(Explore) => "Auto-explore".into(),
+            Game(TravelToFood) => "Go to food".into(),
+            Game(TravelToDose) => "Go to dose".into(),
+            Game(TravelToCompanion) => "Go to companion".into(),
+            Game(Wait) => "Wait a turn".into(),
+            Game(Rest) => "Rest".into(),
+            Meta(Quit) => "Quit".into(),
+            Meta(NewGame) => "New game".into(),
+            Meta(ToggleFullscreen) => "Toggle fullscreen".into(),
+            Meta(CycleColorTheme) => "Change colour theme".into(),
+            Meta(KeyBindings) => "Key bindings".into(),
+            Meta(Look) => "Look around".into(),
+            Meta(MessageLog) => "Message log".into(),
+            Meta(Help) => "Help".into(),
+            Meta(ToggleCheats) => "Toggle cheats".into(),
+            Meta(CheatInvincible) => "Cheat: invincibility".into(),
+            Meta(CheatFood) => "Cheat: food".into(),
+            Meta(PauseReplay) => "Pause replay".into(),
+            Meta(StepReplay) => "Step replay".into(),
+        }
+    }
+
+    /// Whether the action does anything during a regular game.
+    fn while_playing(self) -> bool {
+        match self {
+            Action::Meta(MetaAction::PauseReplay) |
+            Action::Meta(MetaAction::StepReplay) => false,
+            _ => true,
+        }
+    }
+
+    /// Whether the action does anything while watching a replay.
+    fn while_replaying(self) -> bool {
+        match self {
+            Action::Game(_) => false,
+            Action::Meta(_) => true,
+        }
+    }
+
+    /// Two actions can share a key as long as they're never active
+    /// at the same time.
+    fn overlaps(self, other: Action) -> bool {
+        (self.while_playing() && other.while_playing()) ||
+            (self.while_replaying() && other.while_replaying())
+    }
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub key: Key,
+    pub action: Action,
+}
+
+
+/// The mapping from the pressed keys to actions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: Vec<Binding>,
+}
+
+impl Default for KeyBindings {
+    /// Arrows (with Shift and Ctrl for the diagonals), numpad and
+    /// vi-keys all work at the same time.
+    fn default() -> Self {
+        use keys::KeyCode::*;
+
+        let mut bindings = vec![];
+        {
+            let mut game = |key: Key, action: GameAction| {
+                bindings.push(Binding { key, action: Action::Game(action) })
+            };
+
+            // NotEye (arrow keys plus Ctrl and Shift modifiers for
+            // horizontal movement)
+            game(plain(Up), GameAction::N);
+            game(plain(Down), GameAction::S);
+            game(plain(Left), GameAction::W);
+            game(plain(Right), GameAction::E);
+            game(Key { shift: true, ..plain(Left) }, GameAction::NW);
+            game(Key { shift: true, ..plain(Right) }, GameAction::NE);
+            game(Key { ctrl: true, ..plain(Left) }, GameAction::SW);
+            game(Key { ctrl: true, ..plain(Right) }, GameAction::SE);
+
+            // Numpad (8246 for cardinal and 7193 for diagonal movement)
+            game(plain(NumPad8), GameAction::N);
+            game(plain(NumPad2), GameAction::S);
+            game(plain(NumPad4), GameAction::W);
+            game(plain(NumPad6), GameAction::E);
+            game(plain(NumPad7), GameAction::NW);
+            game(plain(NumPad9), GameAction::NE);
+            game(plain(NumPad1), GameAction::SW);
+            game(plain(NumPad3), GameAction::SE);
+
+            // Vi keys (hjkl for cardinal and yunm for diagonal movement)
+            game(plain(K), GameAction::N);
+            game(plain(J), GameAction::S);
+            game(plain(H), GameAction::W);
+            game(plain(L), GameAction::E);
+            game(plain(Y), GameAction::NW);
+            game(plain(U), GameAction::NE);
+            game(plain(N), GameAction::SW);
+            game(plain(M), GameAction::SE);
+
+            game(plain(E), GameAction::Eat);
+            let inventory_keys = [D1, D2, D3, D4, D5, D6, D7, D8, D9];
+            for (index, &code) in inventory_keys.iter().enumerate() {
+                game(plain(code), GameAction::UseInventory(index as u8 + 1));
+            }
+            game(plain(O), GameAction::Explore);
+            game(plain(G), GameAction::TravelToFood);
+            game(plain(D), GameAction::TravelToDose);
+            game(plain(C), GameAction::TravelToCompanion);
+            game(plain(NumPad5), GameAction::Wait);
+            game(plain(S), GameAction::Wait);
+            game(plain(R), GameAction::Rest);
+        }
+        {
+            let mut meta = |key: Key, action: MetaAction| {
+                bindings.push(Binding { key, action: Action::Meta(action) })
+            };
+            meta(plain(Q), MetaAction::Quit);
+            meta(plain(F5), MetaAction::NewGame);
+            meta(Key { alt: true, ..plain(Enter) }, MetaAction::ToggleFullscreen);
+            meta(plain(F7), MetaAction::CycleColorTheme);
+            meta(plain(F2), MetaAction::KeyBindings);
+            meta(plain(X), MetaAction::Look);
+            meta(plain(P), MetaAction::MessageLog);
+            meta(plain(QuestionMark), MetaAction::Help);
+            meta(plain(F1), MetaAction::Help);
+            meta(plain(F6), MetaAction::ToggleCheats);
+            meta(plain(I), MetaAction::CheatInvincible);
+            meta(plain(F), MetaAction::CheatFood);
+            meta(plain(Space), MetaAction::PauseReplay);
+            meta(plain(Right), MetaAction::StepReplay);
+        }
+        KeyBindings { bindings }
+    }
+}
+
+impl KeyBindings {
+    /// The action bound to the `key`.
+    ///
+    /// A binding with exactly the same modifiers wins. If there is
+    /// none, the binding of the key without any modifiers is used so
+    /// e.g. Shift+Up still moves north.
+    pub fn action(&self, key: Key, replay: bool) -> Option<Action> {
+        let active = |binding: &&Binding| if replay {
+            binding.action.while_replaying()
+        } else {
+            binding.action.while_playing()
+        };
+        self.bindings
+            .iter()
+            .filter(&active)
+            .find(|binding| binding.key == key)
+            .or_else(|| {
+                self.bindings
+                    .iter()
+                    .filter(&active)
+                    .find(|binding| binding.key == plain(key.code))
+            })
+            .map(|binding| binding.action)
+    }
+
+    /// All the keys bound to the `action`.
+    pub fn keys(&self, action: Action) -> Vec<Key> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.action == action)
+            .map(|binding| binding.key)
+            .collect()
+    }
+
+    /// Bind the `key` to the `action` unless it's already used by an
+    /// action that's active at the same time.
+    pub fn bind(&mut self, key: Key, action: Action) -> Result<(), String> {
+        if let Some(conflict) = self.conflict(key, action) {
+            return Err(format!(
+                "{} is already used for: {}",
+                key_name(key),
+                conflict.description()
+            ));
+        }
+        let binding = Binding { key, action };
+        if !self.bindings.contains(&binding) {
+            self.bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    /// Remove all the keys bound to the `action`.
+    pub fn clear(&mut self, action: Action) -> Result<(), String> {
+        // NOTE: without a key, there'd be no way back to the key
+        // bindings screen short of editing the settings file.
+        if action == Action::Meta(MetaAction::KeyBindings) {
+            return Err(format!("{} needs at least one key", action.description()));
+        }
+        self.bindings.retain(|binding| binding.action != action);
+        Ok(())
+    }
+
+    /// The action that would conflict with binding `key` to `action`.
+    fn conflict(&self, key: Key, action: Action) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|binding| {
+                binding.key == key && binding.action != action && binding.action.overlaps(action)
+            })
+            .map(|binding| binding.action)
+    }
+
+    /// Give the actions that have no keys at all their default ones.
+    ///
+    /// The settings saved by an older version don't know about the
+    /// actions added since, this way they still get their keys. A
+    /// default key that's been taken by something else is left out.
+    ///
+    /// NOTE: this also brings back the keys of an action the player
+    /// cleared on purpose. The file doesn't tell the two apart.
+    fn add_missing_defaults(&mut self) {
+        let defaults = KeyBindings::default();
+        let missing: Vec<Action> = defaults
+            .bindings
+            .iter()
+            .map(|binding| binding.action)
+            .filter(|&action| self.keys(action).is_empty())
+            .collect();
+        for binding in defaults.bindings {
+            let free = self.conflict(binding.key, binding.action).is_none();
+            if missing.contains(&binding.action) && free {
+                self.bindings.push(binding);
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for binding in &self.bindings {
+            if let Some(conflict) = self.conflict(binding.key, binding.action) {
+                return Err(format!(
+                    "{} is bound to both '{}' and '{}'",
+                    key_name(binding.key),
+                    binding.action.description(),
+                    conflict.description()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for KeyBindings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bindings.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Binding>::deserialize(deserializer).map(|bindings| {
+            let mut bindings = KeyBindings { bindings };
+            bindings.add_missing_defaults();
+            bindings
+        })
+    }
+}
+
+
+fn plain(code: KeyCode) -> Key {
+    Key {
+        code,
+        alt: false,
+        ctrl: false,
+        shift: false,
+    }
+}
+
+
+/// The human-readable name of the key, e.g. "Ctrl+Left" or "Num 8".
+pub fn key_name(key: Key) -> String {
+    use keys::KeyCode::*;
+    let mut result = String::new();
+    if key.ctrl {
+        result.push_str("Ctrl+");
+    }
+    if key.alt {
+        result.push_str("Alt+");
+    }
+    if key.shift {
+        result.push_str("Shift+");
+    }
+    let code = match key.code {
+        D1 => "1".into(),
+        D2 => "2".into(),
+        D3 => "3".into(),
+        D4 => "4".into(),
+        D5 => "5".into(),
+        D6 => "6".into(),
+        D7 => "7".into(),
+        D8 => "8".into(),
+        D9 => "9".into(),
+        D0 => "0".into(),
+        NumPad0 => "Num 0".into(),
+        NumPad1 => "Num 1".into(),
+        NumPad2 => "Num 2".into(),
+        NumPad3 => "Num 3".into(),
+        NumPad4 => "Num 4".into(),
+        NumPad5 => "Num 5".into(),
+        NumPad6 => "Num 6".into(),
+        NumPad7 => "Num 7".into(),
+        NumPad8 => "Num 8".into(),
+        NumPad9 => "Num 9".into(),
+        QuestionMark => "?".into(),
+        // NOTE: the letters, function keys, arrows, etc. are
+        // readable as they are.
+        code => format!("{:?}", code),
+    };
+    result.push_str(&code);
+    result
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::Action::*;
+    use super::GameAction::*;
+
+    #[test]
+    fn default_bindings_are_valid() {
+        assert_eq!(KeyBindings::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn modifiers_fall_back_to_the_plain_key() {
+        let bindings = KeyBindings::default();
+        let shift_up = Key { shift: true, ..plain(KeyCode::Up) };
+        let shift_left = Key { shift: true, ..plain(KeyCode::Left) };
+        assert_eq!(bindings.action(shift_up, false), Some(Game(N)));
+        assert_eq!(bindings.action(shift_left, false), Some(Game(NW)));
+    }
+
+    #[test]
+    fn replay_keys_dont_conflict_with_movement() {
+        let bindings = KeyBindings::default();
+        let right = plain(KeyCode::Right);
+        assert_eq!(bindings.action(right, false), Some(Game(E)));
+        assert_eq!(bindings.action(right, true), Some(Meta(MetaAction::StepReplay)));
+    }
+
+    #[test]
+    fn binding_a_used_key_is_a_conflict() {
+        let mut bindings = KeyBindings::default();
+        assert!(bindings.bind(plain(KeyCode::K), Game(S)).is_err());
+        assert!(bindings.bind(plain(KeyCode::Q), Game(S)).is_err());
+        assert!(bindings.bind(plain(KeyCode::G), Game(S)).is_err());
+        assert!(bindings.bind(plain(KeyCode::Z), Game(S)).is_ok());
+        assert_eq!(bindings.action(plain(KeyCode::Z), false), Some(Game(S)));
+    }
+
+    #[test]
+    fn key_bindings_screen_cant_be_unbound() {
+        let mut bindings = KeyBindings::default();
+        assert!(bindings.clear(Meta(MetaAction::KeyBindings)).is_err());
+        assert!(bindings.clear(Meta(MetaAction::Quit)).is_ok());
+        assert!(bindings.keys(Meta(MetaAction::Quit)).is_empty());
+    }
+
+    #[test]
+    fn old_settings_get_the_new_actions() {
+        use serde_json;
+        let mut old = KeyBindings::default();
+        old.clear(Game(Explore)).unwrap();
+        old.clear(Game(TravelToFood)).unwrap();
+        old.bind(plain(KeyCode::G), Game(Eat)).unwrap();
+        let json = serde_json::to_string(&old).unwrap();
+        let loaded: KeyBindings = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.keys(Game(Explore)), vec![plain(KeyCode::O)]);
+        // NOTE: G is where the player wants to eat now
+        assert!(loaded.keys(Game(TravelToFood)).is_empty());
+        assert_eq!(loaded.action(plain(KeyCode::G), false), Some(Game(Eat)));
+        assert_eq!(loaded.validate(), Ok(()));
+    }
+}
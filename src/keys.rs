@@ -5,8 +5,11 @@ use std::iter::IntoIterator;
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Key {
     pub code: KeyCode,
+    #[serde(default)]
     pub alt: bool,
+    #[serde(default)]
     pub ctrl: bool,
+    #[serde(default)]
     pub shift: bool,
 }
 
@@ -78,6 +81,7 @@ pub enum KeyCode {
     Enter,
     Space,
     Esc,
+    QuestionMark,
 }
 
 
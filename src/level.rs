@@ -1,7 +1,7 @@
 use blocker;
 use color::{self, Color};
 use graphics::Render;
-use item::Item;
+use item::{Equipment, Item};
 use point;
 
 use std::collections::HashMap;
@@ -21,21 +21,22 @@ impl Into<point::Point> for LevelPosition {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Cell {
     pub tile: Tile,
     pub items: Vec<Item>,
+    pub equipment: Option<Equipment>,
     pub explored: bool,
 }
 
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum TileKind {
     Empty,
     Tree,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Tile {
     pub kind: TileKind,
     pub fg_color: Color,
@@ -86,6 +87,7 @@ impl Level {
                     Cell {
                         tile: Tile::new(TileKind::Empty),
                         items: vec![],
+                        equipment: None,
                         explored: false,
                     }
                 })
@@ -93,6 +95,24 @@ impl Level {
         }
     }
 
+    /// Create a level out of previously stored cells (see
+    /// `into_cells`). The level will not have any monsters.
+    pub fn from_cells(width: i32, height: i32, cells: Vec<Cell>) -> Level {
+        let dimensions = (width, height).into();
+        assert!(dimensions > (0, 0));
+        assert_eq!(cells.len(), (width * height) as usize);
+        Level {
+            dimensions,
+            monsters: HashMap::new(),
+            map: cells,
+        }
+    }
+
+    /// Consume the level and return all its cells ordered row by row.
+    pub fn into_cells(self) -> Vec<Cell> {
+        self.map
+    }
+
     /// Convert a bare Point into LevelPosition. Panics when the point
     /// is not inside the level.
     pub fn level_position(&self, pos: point::Point) -> LevelPosition {
@@ -0,0 +1,260 @@
+use ai::{AIState, Behavior};
+use blocker::Blocker;
+use formula::{self, Balance};
+use item::{Equipment, Item};
+use level::TileKind;
+use monster::{CompanionBonus, Kind, Monster};
+use player::{Bonus, Modifier, Player};
+use point::Point;
+use rect::Rectangle;
+use state::State;
+
+
+/// Describe what the player knows about the given world position:
+/// the tile, the player, the items and the monster there.
+///
+/// Follows the same visibility rules as the rendering so it never
+/// tells the player more than they could see on the screen.
+pub fn describe(state: &State, pos: Point) -> Vec<String> {
+    // NOTE: this mirrors the bonus hack in `render::render_game`
+    let bonus = if state.endgame_screen_visible || state.cheating {
+        Bonus::UncoverMap
+    } else {
+        state.player.bonus
+    };
+    let radius = formula::exploration_radius(state.player.mind);
+    let in_fov = state.player.pos.distance(pos) < (radius as f32);
+
+    let cell = match state.world.cell(pos) {
+        Some(cell) if in_fov || cell.explored || bonus == Bonus::UncoverMap => cell,
+        _ => return vec!["You haven't explored this place.".into()],
+    };
+
+    let mut lines = vec![];
+    lines.push(match cell.tile.kind {
+        TileKind::Empty => "Empty ground".into(),
+        TileKind::Tree if state.player.has_equipment(Equipment::Pickaxe) => {
+            "A tree. Walk into it to cut it down with your pickaxe.".into()
+        }
+        TileKind::Tree => "A tree. It's in the way until someone cuts it down.".into(),
+    });
+    if !in_fov {
+        lines.push("You can't see it from here.".into());
+    }
+
+    if pos == state.player.pos {
+        lines.extend(describe_player(&state.player));
+    }
+
+    let see_contents = in_fov || bonus == Bonus::UncoverMap || bonus == Bonus::SeeMonstersAndItems;
+    if see_contents || cell.explored {
+        let item_archetypes = state.world.item_archetypes();
+        for item in &cell.items {
+            let name = &item_archetypes.archetype(item.kind).name;
+            lines.extend(describe_item(item, name, &state.player, &state.balance));
+        }
+        if let Some(equipment) = cell.equipment {
+            lines.push(format!("{}: {}", equipment.name(), equipment.description()));
+        }
+    }
+
+    if see_contents {
+        let area = Rectangle::from_point_and_size(pos, (1, 1).into());
+        for monster in state.world.monsters(area).filter(|m| m.position == pos) {
+            lines.extend(describe_monster(monster));
+        }
+    }
+
+    lines
+}
+
+
+fn describe_player(player: &Player) -> Vec<String> {
+    if !player.alive() {
+        return vec!["This is you. You're dead.".into()];
+    }
+    let mut lines = vec![
+        "This is you.".into(),
+        format!("Mind: {}, Will: {}", player.mind, *player.will),
+        format!("Tolerance: {}", player.tolerance),
+    ];
+    for effect in player.effects.active() {
+        lines.push(format!("{} for {} turns", effect.kind.name(), effect.turns_left));
+    }
+    if player.sobriety_tokens > 0 {
+        lines.push(format!("Sobriety tokens: {}", player.sobriety_tokens));
+    }
+    lines
+}
+
+
+fn describe_item(item: &Item, name: &str, player: &Player, balance: &Balance) -> Vec<String> {
+    match item.modifier {
+        Modifier::Intoxication { state_of_mind, tolerance_increase } => {
+            let effective = formula::effective_dose_strength(state_of_mind, player.tolerance);
+            let resist_radius = player.resist_radius(item);
+            let risk = formula::overdose_risk(player.mind, player.tolerance, item, balance);
+            let mut lines = vec![
+                format!("{}: a dose", name),
+                format!("Strength {} ({} with your tolerance)", state_of_mind, effective),
+                format!("Raises your tolerance by {}", tolerance_increase),
+                // NOTE: the dose pulls the player in when they're
+                // closer than the `resist_radius`.
+                if resist_radius > 2 {
+                    format!("Pulls you in from {} tiles away", resist_radius - 1)
+                } else if resist_radius == 2 {
+                    "Pulls you in from 1 tile away".into()
+                } else {
+                    "Your Will is strong enough to resist it".into()
+                },
+            ];
+            if risk >= 1.0 {
+                lines.push("Using it now would make you overdose!".into());
+            } else if risk > 0.0 {
+                lines.push("It would get you dangerously close to an overdose".into());
+            }
+            lines
+        }
+        modifier => vec![format!("{}: {}", name, describe_modifier(modifier))],
+    }
+}
+
+
+fn describe_monster(monster: &Monster) -> Vec<String> {
+    let mut lines = vec![];
+    if monster.kind == Kind::Npc {
+        lines.push("A person".into());
+        if monster.accompanying_player {
+            lines.push("They're accompanying you".into());
+        } else {
+            lines.push("Bump into them while sober to have them join you".into());
+        }
+        if let Some(bonus) = monster.companion_bonus {
+            lines.push(describe_companion_bonus(bonus).into());
+        }
+        return lines;
+    }
+
+    lines.push(monster.kind.name().into());
+    let attack = if monster.die_after_attack {
+        format!("Attack: {} (disappears afterwards)", describe_modifier(monster.attack))
+    } else {
+        format!("Attack: {}", describe_modifier(monster.attack))
+    };
+    lines.push(attack);
+    if monster.kind == Kind::Anxiety {
+        lines.push("Defeating enough of them strengthens your Will".into());
+    }
+    if !monster.blockers.contains(Blocker::WALL) {
+        lines.push("It cuts its way through the trees".into());
+    }
+    if monster.behavior != Behavior::Friendly {
+        lines.push(match monster.ai_state {
+            AIState::Idle => "It hasn't noticed you".into(),
+            AIState::Chasing => "It's chasing you".into(),
+            AIState::CheckingOut(_) => "It's checking something out".into(),
+        });
+    }
+    lines
+}
+
+
+fn describe_companion_bonus(bonus: CompanionBonus) -> &'static str {
+    match bonus {
+        CompanionBonus::DoubleWillGrowth => "Your Will grows twice as fast with them",
+        CompanionBonus::HalveExhaustion => "Your withdrawal is half as fast with them",
+        CompanionBonus::DoubleActionPoints => "You move twice as fast with them",
+    }
+}
+
+
+/// What the modifier does to the player, e.g. "-1 Will".
+pub fn describe_modifier(modifier: Modifier) -> String {
+    match modifier {
+        Modifier::Death => "kills you".into(),
+        Modifier::Attribute { will, state_of_mind } => {
+            let mut effects = vec![];
+            if will != 0 {
+                effects.push(format!("{:+} Will", will));
+            }
+            if state_of_mind > 0 {
+                effects.push(format!("eases withdrawal by {}", state_of_mind));
+            } else if state_of_mind < 0 {
+                effects.push(format!("drains your mind by {}", -state_of_mind));
+            }
+            if effects.is_empty() {
+                "does nothing".into()
+            } else {
+                effects.join(", ")
+            }
+        }
+        Modifier::Intoxication { state_of_mind, .. } => {
+            format!("gets you high (strength {})", state_of_mind)
+        }
+        Modifier::Panic(turns) => format!("makes you panic for {} turns", turns),
+        Modifier::Stun(turns) => format!("stuns you for {} turns", turns),
+        Modifier::Medication(turns) => format!("stops the withdrawal for {} turns", turns),
+        Modifier::DoseBlocker(turns) => format!("stops doses pulling you in for {} turns", turns),
+        Modifier::SobrietyToken => "doses pull you in less, for good".into(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{describe_item, describe_modifier};
+    use color;
+    use formula::Balance;
+    use item::{Explosion, ExplosionShape, Item, Kind};
+    use player::{Modifier, Player};
+    use point::Point;
+
+    fn dose(irresistible: i32) -> Item {
+        Item {
+            kind: Kind(0),
+            glyph: 'i',
+            color: color::gui_text,
+            modifier: Modifier::Intoxication { state_of_mind: 100, tolerance_increase: 1 },
+            irresistible,
+            explosion: Explosion { shape: ExplosionShape::Square, radius: 6, initial_radius: 2 },
+        }
+    }
+
+    #[test]
+    fn dose_pull_distance() {
+        let balance = Balance::default();
+        let player = Player::new(Point::new(0, 0), false, &balance);
+        // NOTE: the player starts with 2 Will so the resist radius
+        // is the `irresistible` value.
+        let pull = |irresistible| describe_item(&dose(irresistible), "Dose", &player, &balance)[3].clone();
+        assert_eq!(pull(4), "Pulls you in from 3 tiles away");
+        assert_eq!(pull(2), "Pulls you in from 1 tile away");
+        assert_eq!(pull(1), "Your Will is strong enough to resist it");
+        assert_eq!(pull(0), "Your Will is strong enough to resist it");
+    }
+
+    #[test]
+    fn attribute_lists_all_effects() {
+        let modifier = Modifier::Attribute { will: -1, state_of_mind: -20 };
+        assert_eq!(describe_modifier(modifier), "-1 Will, drains your mind by 20");
+    }
+
+    #[test]
+    fn food_eases_withdrawal() {
+        let modifier = Modifier::Attribute { will: 0, state_of_mind: 10 };
+        assert_eq!(describe_modifier(modifier), "eases withdrawal by 10");
+    }
+
+    #[test]
+    fn empty_attribute_does_nothing() {
+        let modifier = Modifier::Attribute { will: 0, state_of_mind: 0 };
+        assert_eq!(describe_modifier(modifier), "does nothing");
+    }
+
+    #[test]
+    fn timed_effects_mention_the_duration() {
+        assert_eq!(describe_modifier(Modifier::Stun(3)), "stuns you for 3 turns");
+        assert_eq!(describe_modifier(Modifier::Panic(4)), "makes you panic for 4 turns");
+        assert_eq!(describe_modifier(Modifier::Medication(5)), "stops the withdrawal for 5 turns");
+    }
+}
@@ -5,6 +5,7 @@
 #[macro_use]
 extern crate bitflags;
 extern crate rand;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
@@ -34,6 +35,7 @@ extern crate rustbox;
 #[cfg(feature = "remote")]
 extern crate zmq;
 
+use settings::Settings;
 use state::State;
 use std::path::Path;
 
@@ -41,14 +43,19 @@ mod ai;
 mod animation;
 mod blocker;
 mod color;
+mod data;
 mod engine;
 mod formula;
 mod game;
 mod generators;
 mod graphics;
+mod help;
 mod item;
+mod keymap;
 mod keys;
 mod level;
+mod look;
+mod messages;
 mod monster;
 mod pathfinding;
 mod player;
@@ -56,9 +63,13 @@ mod point;
 mod ranged_int;
 mod rect;
 mod render;
+mod settings;
 mod state;
 mod stats;
+mod status;
 mod timer;
+mod tips;
+mod tutorial;
 mod util;
 mod world;
 
@@ -136,6 +147,7 @@ fn run_opengl(
     display_size: point::Point,
     default_background: color::Color,
     window_title: &str,
+    settings: Settings,
     state: State,
     update: engine::UpdateFn,
 ) {
@@ -144,6 +156,7 @@ fn run_opengl(
         display_size,
         default_background,
         window_title,
+        settings,
         state,
         update,
     );
@@ -154,6 +167,7 @@ fn run_opengl(
     _display_size: point::Point,
     _default_background: color::Color,
     _window_title: &str,
+    _settings: Settings,
     _state: State,
     _update: engine::UpdateFn,
 ) {
@@ -191,10 +205,7 @@ fn run_remote(
 
 #[cfg(feature = "cli")]
 fn process_cli_and_run_game(
-    display_size: point::Point,
     world_size: point::Point,
-    map_size: i32,
-    panel_width: i32,
     default_background: color::Color,
     title: &str,
     update: engine::UpdateFn,
@@ -228,11 +239,23 @@ fn process_cli_and_run_game(
                 .value_name("FILE")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("settings")
+                .help("Read the settings from this file instead of the default location.")
+                .long("settings")
+                .value_name("FILE")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("exit-after")
                 .help("Exit after the game or replay has finished")
                 .long("exit-after"),
         )
+        .arg(
+            Arg::with_name("tutorial")
+                .help("Play the tutorial instead of a regular game.")
+                .long("tutorial"),
+        )
         .arg(
             Arg::with_name("invincible")
                 .help("Makes the player character invincible. They do not die.")
@@ -265,6 +288,14 @@ fn process_cli_and_run_game(
         ))
         .get_matches();
 
+    let settings = match matches.value_of("settings") {
+        Some(path) => Settings::load(path.into()),
+        None => settings::default_path().map(Settings::load).unwrap_or_default(),
+    };
+    let map_size = settings.map_size;
+    let panel_width = settings.panel_width;
+    let display_size = (map_size + panel_width, map_size).into();
+
     let state = if let Some(replay) = matches.value_of("replay") {
         if matches.is_present("replay-file") {
             panic!(
@@ -272,6 +303,9 @@ fn process_cli_and_run_game(
                     game, not replay."
             );
         }
+        if matches.is_present("tutorial") {
+            panic!("The `tutorial` option can't be used during a replay.");
+        }
         let replay_path = Path::new(replay);
         State::replay_game(
             world_size,
@@ -292,17 +326,29 @@ fn process_cli_and_run_game(
         }
         let replay_file = match matches.value_of("replay-file") {
             Some(file) => Some(file.into()),
-            None => state::generate_replay_path(),
+            None if settings.record_replays => state::generate_replay_path(),
+            None => None,
         };
-        State::new_game(
-            world_size,
-            map_size,
-            panel_width,
-            display_size,
-            matches.is_present("exit-after"),
-            replay_file,
-            matches.is_present("invincible"),
-        )
+        if matches.is_present("tutorial") {
+            State::new_tutorial(
+                world_size,
+                map_size,
+                panel_width,
+                display_size,
+                matches.is_present("exit-after"),
+                matches.is_present("invincible"),
+            )
+        } else {
+            State::new_game(
+                world_size,
+                map_size,
+                panel_width,
+                display_size,
+                matches.is_present("exit-after"),
+                replay_file,
+                matches.is_present("invincible"),
+            )
+        }
     };
 
 
@@ -328,17 +374,14 @@ fn process_cli_and_run_game(
     } else if matches.is_present("remote") {
         run_remote(display_size, default_background, title, state, update);
     } else {
-        run_opengl(display_size, default_background, title, state, update);
+        run_opengl(display_size, default_background, title, settings, state, update);
     }
 }
 
 
 #[cfg(not(feature = "cli"))]
 fn process_cli_and_run_game(
-    _display_size: point::Point,
     _world_size: point::Point,
-    _map_size: i32,
-    _panel_width: i32,
     _default_background: color::Color,
     _title: &str,
     _update: engine::UpdateFn,
@@ -352,10 +395,9 @@ fn process_cli_and_run_game(
 #[no_mangle]
 pub extern "C" fn initialise() -> *mut State {
     let state = {
-        // NOTE: at our current font, the height of 43 is the maximum
-        // value for 1336x768 monitors.
-        let map_size = 43;
-        let panel_width = 20;
+        let settings = Settings::default();
+        let map_size = settings.map_size;
+        let panel_width = settings.panel_width;
         let display_size: point::Point = (map_size + panel_width, map_size).into();
         // NOTE: 2 ^ 30
         let world_size: point::Point = (1_073_741_824, 1_073_741_824).into();
@@ -393,7 +435,7 @@ pub extern "C" fn update(state_ptr: *mut State, dt_ms: u32) {
     let fps = 60;
     let keys: Vec<keys::Key> = vec![];
     let mouse: engine::Mouse = Default::default();
-    let mut settings = engine::Settings{ fullscreen: false };
+    let mut settings = Settings::default();
     let mut drawcalls: Vec<engine::Draw> = vec![];
 
     let result = game::update(
@@ -585,6 +627,7 @@ fn from_js_keycode(js_keycode: i32) -> Option<keys::KeyCode> {
         Enter,
         Space,
         Esc,
+        QuestionMark,
     ];
 
     if js_keycode >= 0 && (js_keycode as usize) < map.len() {
@@ -597,15 +640,9 @@ fn from_js_keycode(js_keycode: i32) -> Option<keys::KeyCode> {
 
 
 fn main() {
-    // NOTE: at our current font, the height of 43 is the maximum
-    // value for 1336x768 monitors.
-    let map_size = 43;
-    let panel_width = 20;
-    let display_size = (map_size + panel_width, map_size).into();
     // NOTE: 2 ^ 30
     let world_size = (1_073_741_824, 1_073_741_824).into();
     let title = "Dose Response";
 
-    process_cli_and_run_game(display_size, world_size, map_size, panel_width,
-                             color::background, title, game::update);
+    process_cli_and_run_game(world_size, color::background, title, game::update);
 }
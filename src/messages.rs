@@ -0,0 +1,189 @@
+use item::Equipment;
+use keymap::GameAction;
+use monster::{self, CompanionBonus};
+use util;
+
+
+/// How many messages the log keeps. The older ones are dropped.
+const MAX_MESSAGES: usize = 1000;
+
+
+/// Something that happened in the game that the player should know
+/// about.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    Attacked(monster::Kind),
+    Defeated(monster::Kind),
+    AteFood,
+    DoseTookEffect,
+    WillIncreased(i32),
+    WillDecreased(i32),
+    Stunned(i32),
+    Panicked(i32),
+    CompanionJoined(Option<CompanionBonus>),
+    CompanionLeft,
+    Equipped(Equipment),
+    TreeCutDown,
+    Medicated(i32),
+    DosesBlocked(i32),
+    SobrietyToken(i32),
+    Invincible,
+    /// The auto-travel found nothing to go to.
+    NothingInSight(GameAction),
+    NothingToExplore,
+    TravelInterrupted(&'static str),
+    StoppedResting(&'static str),
+    /// The chunk cache couldn't write to the disk.
+    ChunkCacheFailed,
+}
+
+impl Event {
+    pub fn text(self) -> String {
+        use self::Event::*;
+        match self {
+            Attacked(kind) => format!("{} attacks you.", kind.name()),
+            Defeated(kind) => format!("You defeat {}.", kind.name()),
+            AteFood => "You eat some food.".into(),
+            DoseTookEffect => "The dose takes effect.".into(),
+            WillIncreased(will) => format!("Your Will grows to {}.", will),
+            WillDecreased(will) => format!("Your Will drops to {}.", will),
+            Stunned(turns) => format!("You're stunned for {} turns.", turns),
+            Panicked(turns) => format!("You panic for {} turns.", turns),
+            CompanionJoined(Some(CompanionBonus::DoubleWillGrowth)) => {
+                "Someone joins you. Your Will grows faster with them.".into()
+            }
+            CompanionJoined(Some(CompanionBonus::HalveExhaustion)) => {
+                "Someone joins you. Your withdrawal is slower with them.".into()
+            }
+            CompanionJoined(Some(CompanionBonus::DoubleActionPoints)) => {
+                "Someone joins you. You move faster with them.".into()
+            }
+            CompanionJoined(None) => "Someone joins you.".into(),
+            CompanionLeft => "Your companion leaves you.".into(),
+            Equipped(equipment) => format!(
+                "You pick up the {}. Now you can {}.",
+                equipment.name().to_lowercase(),
+                equipment.description()
+            ),
+            TreeCutDown => "You cut the tree down.".into(),
+            Medicated(turns) => {
+                format!("The medication holds off your withdrawal for {} turns.", turns)
+            }
+            DosesBlocked(turns) => format!("Doses can't pull you in for {} turns.", turns),
+            SobrietyToken(1) => "You earn a sobriety token. Doses pull you in less now.".into(),
+            SobrietyToken(count) => format!(
+                "You earn another sobriety token. You have {} now.",
+                count
+            ),
+            Invincible => "You're invincible now.".into(),
+            NothingInSight(GameAction::TravelToFood) => "There's no food in sight.".into(),
+            NothingInSight(GameAction::TravelToDose) => "There's no dose in sight.".into(),
+            NothingInSight(GameAction::TravelToCompanion) => {
+                "There's no one to join you in sight.".into()
+            }
+            NothingInSight(_) => "There's nothing in sight.".into(),
+            NothingToExplore => "There's nothing left to explore nearby.".into(),
+            TravelInterrupted(reason) => format!("You stop: {}.", reason),
+            StoppedResting(reason) => format!("You stop resting: {}.", reason),
+            ChunkCacheFailed => {
+                "Couldn't save the distant parts of the world to the disk. \
+                 They're kept in memory from now on.".into()
+            }
+        }
+    }
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Message {
+    pub turn: i32,
+    pub event: Event,
+}
+
+
+/// All the events of the game so far, oldest first.
+#[derive(Clone, Debug, Default)]
+pub struct MessageLog {
+    turn: i32,
+    messages: Vec<Message>,
+}
+
+impl MessageLog {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The events added from now on happened during the given turn.
+    pub fn new_turn(&mut self, turn: i32) {
+        self.turn = turn;
+    }
+
+    pub fn add(&mut self, event: Event) {
+        if self.messages.len() >= MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.messages.push(Message {
+            turn: self.turn,
+            event,
+        });
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Whether the message happened during the current turn.
+    pub fn is_new(&self, message: &Message) -> bool {
+        message.turn == self.turn
+    }
+
+    /// The messages wrapped to the `width`, each line paired with
+    /// the message it's from.
+    pub fn lines(&self, width: usize) -> Vec<(String, Message)> {
+        let mut result = vec![];
+        for &message in &self.messages {
+            for line in util::wrap_text(&message.event.text(), width) {
+                result.push((line, message));
+            }
+        }
+        result
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Event, MessageLog, MAX_MESSAGES};
+
+    #[test]
+    fn messages_remember_their_turn() {
+        let mut log = MessageLog::new();
+        log.add(Event::AteFood);
+        log.new_turn(5);
+        log.add(Event::DoseTookEffect);
+        let turns: Vec<_> = log.messages().iter().map(|m| m.turn).collect();
+        assert_eq!(turns, vec![0, 5]);
+        assert!(!log.is_new(&log.messages()[0]));
+        assert!(log.is_new(&log.messages()[1]));
+    }
+
+    #[test]
+    fn old_messages_are_dropped() {
+        let mut log = MessageLog::new();
+        for _ in 0..MAX_MESSAGES {
+            log.add(Event::AteFood);
+        }
+        log.add(Event::DoseTookEffect);
+        assert_eq!(log.messages().len(), MAX_MESSAGES);
+        assert_eq!(log.messages().last().unwrap().event, Event::DoseTookEffect);
+    }
+
+    #[test]
+    fn long_messages_wrap() {
+        let mut log = MessageLog::new();
+        log.add(Event::CompanionJoined(None));
+        log.add(Event::AteFood);
+        let lines: Vec<_> = log.lines(10).into_iter().map(|(line, _)| line).collect();
+        assert_eq!(lines, vec!["Someone", "joins you.", "You eat", "some food."]);
+    }
+}
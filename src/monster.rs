@@ -1,8 +1,8 @@
-use self::Kind::*;
-
 use ai::{self, AIState, Behavior, PlayerInfo, Update};
 use blocker::Blocker;
-use color::{self, Color};
+use color::Color;
+use data::MonsterArchetype;
+use formula::Balance;
 use game::Action;
 use graphics::Render;
 use player::Modifier;
@@ -13,8 +13,33 @@ use std::time::Duration;
 use world::World;
 
 
-#[derive(Clone, PartialEq, Debug)]
+/// World-unique identifier of a monster.
+///
+/// It's derived from the chunk the monster was generated in and its
+/// order in that chunk. That makes it stable regardless of the order
+/// in which the chunks were generated and it doesn't change when the
+/// monster moves to another chunk.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MonsterId {
+    chunk_x: i32,
+    chunk_y: i32,
+    index: u32,
+}
+
+impl MonsterId {
+    pub fn new(chunk_position: Point, index: usize) -> Self {
+        MonsterId {
+            chunk_x: chunk_position.x,
+            chunk_y: chunk_position.y,
+            index: index as u32,
+        }
+    }
+}
+
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Monster {
+    pub id: MonsterId,
     pub kind: Kind,
     pub position: Point,
     pub dead: bool,
@@ -26,6 +51,8 @@ pub struct Monster {
     pub path: Vec<Point>,
     pub trail: Option<Point>,
     pub color: Color,
+    pub glyph: char,
+    pub attack: Modifier,
     pub companion_bonus: Option<CompanionBonus>,
     pub accompanying_player: bool,
 
@@ -44,6 +71,19 @@ pub enum Kind {
     Npc,
 }
 
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Anxiety => "Anxiety",
+            Kind::Depression => "Depression",
+            Kind::Hunger => "Hunger",
+            Kind::Shadows => "Shadows",
+            Kind::Voices => "Voices",
+            Kind::Npc => "Person",
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum CompanionBonus {
     DoubleWillGrowth,
@@ -67,85 +107,31 @@ impl Rand for CompanionBonus {
 }
 
 impl Monster {
-    pub fn new(kind: Kind, position: Point) -> Monster {
-        let die_after_attack = match kind {
-            Shadows | Voices => true,
-            Anxiety | Depression | Hunger | Npc => false,
-        };
-
-        let max_ap = match kind {
-            Depression => 2,
-            Anxiety | Hunger | Shadows | Voices | Npc => 1,
-        };
-
-        let behavior = match kind {
-            Depression => Behavior::LoneAttacker,
-            Anxiety => Behavior::LoneAttacker,
-            Hunger => Behavior::PackAttacker,
-            Shadows => Behavior::LoneAttacker,
-            Voices => Behavior::LoneAttacker,
-            Npc => Behavior::Friendly,
-        };
-
-        let invincible = match kind {
-            Npc => true,
-            _ => false,
-        };
-
-        // NOTE: NPCs can't walk into the player, monsters can
-        let blockers = match kind {
-            Npc => Blocker::PLAYER | Blocker::WALL | Blocker::MONSTER,
-            _ => Blocker::WALL | Blocker::MONSTER,
-        };
-
-        let color = match kind {
-            Depression => color::depression,
-            Anxiety => color::anxiety,
-            Hunger => color::hunger,
-            Shadows => color::shadows,
-            Voices => color::voices,
-            Npc => color::npc_speed,
-        };
-
-
+    pub fn new(id: MonsterId, archetype: &MonsterArchetype, position: Point) -> Monster {
         Monster {
-            kind,
+            id,
+            kind: archetype.kind,
             position,
             dead: false,
-            die_after_attack,
-            invincible,
-            behavior,
+            die_after_attack: archetype.die_after_attack,
+            invincible: archetype.invincible,
+            behavior: archetype.behavior,
             ai_state: AIState::Idle,
             ap: 0,
-            max_ap,
-            blockers,
+            max_ap: archetype.max_ap,
+            blockers: archetype.blockers,
             path: vec![],
             trail: None,
-            color,
+            color: archetype.color,
+            glyph: archetype.glyph,
+            attack: archetype.attack,
             companion_bonus: None,
             accompanying_player: false,
         }
     }
 
     pub fn attack_damage(&self) -> Modifier {
-        use player::Modifier::*;
-        match self.kind {
-            Anxiety => Attribute {
-                will: -1,
-                state_of_mind: 0,
-            },
-            Depression => Death,
-            Hunger => Attribute {
-                will: 0,
-                state_of_mind: -20,
-            },
-            Shadows => Panic(4),
-            Voices => Stun(4),
-            Npc => Attribute {
-                will: 0,
-                state_of_mind: 0,
-            },  // NOTE: no-op
-        }
+        self.attack
     }
 
     pub fn act<R: Rng>(
@@ -153,14 +139,15 @@ impl Monster {
         player_info: PlayerInfo,
         world: &mut World,
         rng: &mut R,
+        balance: &Balance,
     ) -> (Update, Action) {
         if self.dead {
             panic!(format!("{:?} is dead, cannot run actions on it.", self));
         }
         match self.behavior {
-            Behavior::LoneAttacker => ai::lone_attacker_act(self, player_info, world, rng),
-            Behavior::PackAttacker => ai::pack_attacker_act(self, player_info, world, rng),
-            Behavior::Friendly => ai::friendly_act(self, player_info, world, rng),
+            Behavior::LoneAttacker => ai::lone_attacker_act(self, player_info, world, rng, balance),
+            Behavior::PackAttacker => ai::pack_attacker_act(self, player_info, world, rng, balance),
+            Behavior::Friendly => ai::friendly_act(self, player_info, world, rng, balance),
         }
     }
 
@@ -188,14 +175,7 @@ impl Monster {
     }
 
     pub fn glyph(&self) -> char {
-        match self.kind {
-            Anxiety => 'a',
-            Depression => 'D',
-            Hunger => 'h',
-            Shadows => 'S',
-            Voices => 'v',
-            Npc => '@',
-        }
+        self.glyph
     }
 }
 
@@ -1,10 +1,18 @@
 use blocker;
+use level::TileKind;
 use point::Point;
+use rect::Rectangle;
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, VecDeque};
 use std::f32;
 use world::World;
 
+/// The cost of stepping into a tree. Only the monsters that can walk
+/// through trees get there and it takes them an extra turn to cut the
+/// tree down first.
+const TREE_COST: f32 = 2.0;
+
+
 #[derive(Debug)]
 pub struct Path {
     path: Vec<Point>,
@@ -14,7 +22,7 @@ impl Path {
     pub fn find(
         from: Point,
         to: Point,
-        world: &mut World,
+        world: &World,
         blockers: blocker::Blocker,
         player_position: Point,
     ) -> Self {
@@ -55,7 +63,7 @@ impl Path {
         let cost = |current: Point, next: Point| -> f32 {
             assert!((current.x - next.x).abs() <= 1);
             assert!((current.y - next.y).abs() <= 1);
-            1.0
+            step_cost(world, next)
         };
 
         let heuristic = |destination: Point, next: Point| -> f32 {
@@ -149,6 +157,71 @@ impl Iterator for Path {
     }
 }
 
+/// The cost of stepping into the `pos` from one of its neighbours.
+fn step_cost(world: &World, pos: Point) -> f32 {
+    let tree = world.cell(pos).map_or(false, |cell| cell.tile.kind == TileKind::Tree);
+    if tree {
+        TREE_COST
+    } else {
+        1.0
+    }
+}
+
+/// Find the nearest point for which `is_goal` returns true that can
+/// be reached from `from` without leaving `area`.
+///
+/// Returns the goal and the first step towards it. Unlike
+/// `Path::find`, this searches the whole area so it can be used for
+/// destinations that are far away.
+pub fn nearest<F>(
+    from: Point,
+    world: &World,
+    blockers: blocker::Blocker,
+    player_position: Point,
+    area: Rectangle,
+    is_goal: F,
+) -> Option<(Point, Point)>
+where
+    F: Fn(Point) -> bool,
+{
+    let neighbour_offsets: [Point; 8] = [
+        (-1, -1).into(),
+        (-1, 0).into(),
+        (-1, 1).into(),
+        (0, -1).into(),
+        (0, 1).into(),
+        (1, -1).into(),
+        (1, 0).into(),
+        (1, 1).into(),
+    ];
+
+    let mut came_from = HashMap::new();
+    let mut frontier = VecDeque::new();
+    came_from.insert(from, from);
+    frontier.push_back(from);
+
+    while let Some(current) = frontier.pop_front() {
+        if current != from && is_goal(current) {
+            let mut first_step = current;
+            while came_from[&first_step] != from {
+                first_step = came_from[&first_step];
+            }
+            return Some((current, first_step));
+        }
+        for &offset in neighbour_offsets.iter() {
+            let next = current + offset;
+            if area.contains(next) && !came_from.contains_key(&next) &&
+                world.walkable(next, blockers, player_position)
+            {
+                came_from.insert(next, current);
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 struct State {
     cost: f32,
@@ -184,19 +257,33 @@ impl PartialOrd for State {
 
 #[cfg(test)]
 mod test {
-    use super::Path;
-    use level::{Level, Walkability};
+    use super::{nearest, step_cost, Path, TREE_COST};
+    use blocker::Blocker;
+    use level::Tile;
+    use level::TileKind::{Empty, Tree};
     use point::Point;
+    use rect::Rectangle;
+    use world::{self, World, TEST_ORIGIN};
+
+    /// The player is nowhere near the boards.
+    const PLAYER: Point = Point { x: 0, y: 0 };
 
     struct Board {
         start: Point,
         destination: Point,
-        level: Level,
+        area: Rectangle,
+        world: World,
+    }
+
+    /// The board positions in the world coordinates.
+    fn points(board_points: &[(i32, i32)]) -> Vec<Point> {
+        board_points
+            .iter()
+            .map(|&(x, y)| TEST_ORIGIN + (x, y))
+            .collect()
     }
 
     fn make_board(text: &str) -> Board {
-        use level::Tile;
-        use level::TileKind::{Empty, Tree};
         let mut start = Point { x: 0, y: 0 };
         let mut destination = Point { x: 0, y: 0 };
         let mut x = 0;
@@ -209,7 +296,11 @@ mod test {
         assert!(width > 0);
         assert!(lines.iter().all(|line| line.chars().count() == width));
 
-        let mut level = Level::new(width as i32, height as i32);
+        let size = Point::new(width as i32, height as i32);
+        let area = Rectangle::from_point_and_size(TEST_ORIGIN, size);
+        let mut world = world::test_world_of_trees(
+            Rectangle::from_point_and_size(TEST_ORIGIN - (1, 1), size + (2, 2)),
+        );
 
         for line in lines {
             for c in line.chars() {
@@ -235,13 +326,9 @@ mod test {
                     'x' => Tree,
                     _ => unreachable!(),
                 };
-                level.set_tile(
-                    Point {
-                        x: x as i32,
-                        y: y as i32,
-                    },
-                    Tile::new(tile_kind),
-                );
+                if let Some(cell) = world.cell_mut(TEST_ORIGIN + (x as i32, y as i32)) {
+                    cell.tile = Tile::new(tile_kind);
+                }
 
                 x += 1;
             }
@@ -253,9 +340,10 @@ mod test {
         assert!(destination != Point { x: -1, y: -1 });
 
         Board {
-            start: start,
-            destination: destination,
-            level: level,
+            start: TEST_ORIGIN + start,
+            destination: TEST_ORIGIN + destination,
+            area: area,
+            world: world,
         }
     }
 
@@ -272,15 +360,12 @@ mod test {
         let path: Path = Path::find(
             board.start,
             board.destination,
-            &board.level,
-            Walkability::WalkthroughMonsters,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
         );
         assert_eq!(1, path.len());
-        let expected = [(2, 1)]
-            .iter()
-            .cloned()
-            .map(Into::into)
-            .collect::<Vec<Point>>();
+        let expected = points(&[(2, 1)]);
         assert_eq!(expected, path.collect::<Vec<_>>());
     }
 
@@ -298,8 +383,9 @@ mod test {
         let path: Path = Path::find(
             board.start,
             board.destination,
-            &board.level,
-            Walkability::WalkthroughMonsters,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
         );
         assert_eq!(0, path.len());
         let expected: Vec<Point> = vec![];
@@ -319,15 +405,12 @@ mod test {
         let path: Path = Path::find(
             board.start,
             board.destination,
-            &board.level,
-            Walkability::WalkthroughMonsters,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
         );
         assert_eq!(7, path.len());
-        let expected = [(2, 1), (3, 1), (4, 1), (5, 1), (6, 1), (7, 1), (8, 1)]
-            .iter()
-            .cloned()
-            .map(Into::into)
-            .collect::<Vec<Point>>();
+        let expected = points(&[(2, 1), (3, 1), (4, 1), (5, 1), (6, 1), (7, 1), (8, 1)]);
         assert_eq!(expected, path.collect::<Vec<_>>());
     }
 
@@ -344,15 +427,12 @@ s..........
         let path: Path = Path::find(
             board.start,
             board.destination,
-            &board.level,
-            Walkability::WalkthroughMonsters,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
         );
         assert_eq!(3, path.len());
-        let expected = [(1, 1), (2, 2), (3, 3)]
-            .iter()
-            .cloned()
-            .map(Into::into)
-            .collect::<Vec<Point>>();
+        let expected = points(&[(1, 1), (2, 2), (3, 3)]);
         assert_eq!(expected, path.collect::<Vec<_>>());
     }
 
@@ -369,8 +449,9 @@ s..........
         let path: Path = Path::find(
             board.start,
             board.destination,
-            &board.level,
-            Walkability::WalkthroughMonsters,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
         );
         assert_eq!(0, path.len());
     }
@@ -388,15 +469,12 @@ s..........
         let path: Path = Path::find(
             board.start,
             board.destination,
-            &board.level,
-            Walkability::WalkthroughMonsters,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
         );
         assert_eq!(7, path.len());
-        let expected = [(2, 2), (3, 3), (4, 3), (5, 3), (6, 3), (7, 3), (8, 3)]
-            .iter()
-            .cloned()
-            .map(Into::into)
-            .collect::<Vec<Point>>();
+        let expected = points(&[(2, 2), (3, 3), (4, 3), (5, 3), (6, 3), (7, 3), (8, 3)]);
         assert_eq!(expected, path.collect::<Vec<_>>());
     }
 
@@ -414,11 +492,12 @@ s..........
         let path: Path = Path::find(
             board.start,
             board.destination,
-            &board.level,
-            Walkability::WalkthroughMonsters,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
         );
         assert_eq!(9, path.len());
-        let expected = [
+        let expected = points(&[
             (2, 2),
             (2, 3),
             (3, 4),
@@ -428,10 +507,159 @@ s..........
             (7, 3),
             (7, 2),
             (7, 1),
-        ].iter()
-            .cloned()
-            .map(Into::into)
-            .collect::<Vec<Point>>();
+        ]);
         assert_eq!(expected, path.collect::<Vec<_>>());
     }
+
+    #[test]
+    fn test_tree_cost() {
+        let board = make_board(
+            "
+.sx.d
+",
+        );
+        assert_eq!(TREE_COST, step_cost(&board.world, TEST_ORIGIN + (2, 0)));
+        assert_eq!(1.0, step_cost(&board.world, TEST_ORIGIN + (3, 0)));
+    }
+
+    #[test]
+    fn test_trees_block_unless_allowed() {
+        let board = make_board(
+            "
+....x....
+.s..x..d.
+....x....
+",
+        );
+        let path: Path = Path::find(
+            board.start,
+            board.destination,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
+        );
+        assert_eq!(0, path.len());
+
+        let path: Path = Path::find(
+            board.start,
+            board.destination,
+            &board.world,
+            Blocker::empty(),
+            PLAYER,
+        );
+        let steps = path.collect::<Vec<_>>();
+        assert_eq!(Some(&board.destination), steps.last());
+        assert!(
+            steps
+                .iter()
+                .any(|&pos| board.world.cell(pos).map_or(false, |cell| cell.tile.kind == Tree))
+        );
+    }
+
+    #[test]
+    fn test_nearest_unexplored() {
+        let mut board = make_board(
+            "
+.........
+.s......d
+.........
+",
+        );
+        for pos in board.area.points().filter(|pos| pos.x <= TEST_ORIGIN.x + 5) {
+            board.world.cell_mut(pos).unwrap().explored = true;
+        }
+        let world = &board.world;
+        let unexplored = |pos| world.cell(pos).map_or(false, |cell| !cell.explored);
+        let result = nearest(board.start, world, Blocker::WALL, PLAYER, board.area, unexplored);
+        let (goal, first_step) = result.expect("There should be an unexplored cell.");
+        assert_eq!(goal.x, TEST_ORIGIN.x + 6);
+        assert_eq!(first_step.x, TEST_ORIGIN.x + 2);
+        assert_eq!(first_step.tile_distance(board.start), 1);
+    }
+
+    #[test]
+    fn test_nearest_skips_the_start() {
+        let board = make_board(
+            "
+.s.d
+",
+        );
+        let destination = board.destination;
+        let result = nearest(
+            board.start,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
+            board.area,
+            |pos| pos == board.start || pos == destination,
+        );
+        assert_eq!(Some((destination, TEST_ORIGIN + (2, 0))), result);
+    }
+
+    #[test]
+    fn test_nearest_unreachable() {
+        let board = make_board(
+            "
+....x....
+.s..x..d.
+....x....
+",
+        );
+        let destination = board.destination;
+        let result = nearest(
+            board.start,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
+            board.area,
+            |pos| pos == destination,
+        );
+        assert_eq!(None, result);
+    }
+
+    #[test]
+    fn test_nearest_outside_of_the_area() {
+        let board = make_board(
+            "
+.s......d
+",
+        );
+        let destination = board.destination;
+        let area = Rectangle::from_point_and_size(TEST_ORIGIN, Point::new(5, 1));
+        let result = nearest(
+            board.start,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
+            area,
+            |pos| pos == destination,
+        );
+        assert_eq!(None, result);
+    }
+
+    #[test]
+    fn test_nearest_tie_breaking() {
+        let board = make_board(
+            "
+.......
+.*.s.*.
+.......
+",
+        );
+        // NOTE: both goals are two steps away. The neighbours are
+        // visited from the top-left so the left one wins.
+        let goals = points(&[(1, 1), (5, 1)]);
+        let result = nearest(
+            board.start,
+            &board.world,
+            Blocker::WALL,
+            PLAYER,
+            board.area,
+            |pos| goals.contains(&pos),
+        );
+        let (goal, first_step) = result.expect("There should be a goal in reach.");
+        assert_eq!(goals[0], goal);
+        assert_eq!(first_step.x, TEST_ORIGIN.x + 2);
+        assert_eq!(first_step.tile_distance(board.start), 1);
+    }
 }
@@ -1,15 +1,16 @@
 use color::{self, Color};
-use formula::{self, ANXIETIES_PER_WILL, SOBRIETY_COUNTER, WILL, WITHDRAWAL};
+use formula::{self, Balance};
 use graphics::Render;
-use item::Item;
-use monster::{Monster, CompanionBonus};
+use item::{Equipment, Item};
+use monster::{Monster, MonsterId, CompanionBonus};
 use point::Point;
 use ranged_int::Ranged;
+use status::{self, StatusEffects};
 use std::fmt::{Display, Error, Formatter};
 use std::time::Duration;
 
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Modifier {
     Death,
     // TODO: probably rename `state_of_mind` to something like hunger
@@ -24,6 +25,14 @@ pub enum Modifier {
     },
     Panic(i32),
     Stun(i32),
+    /// The Mind doesn't drop during the withdrawal for this many
+    /// turns.
+    Medication(i32),
+    /// Doses can't pull the player in for this many turns.
+    DoseBlocker(i32),
+    /// A permanent Will bonus for staying sober (see
+    /// `Player::sobriety_tokens`).
+    SobrietyToken,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -44,6 +53,18 @@ impl Mind {
     pub fn is_sober(&self) -> bool {
         !self.is_high()
     }
+
+    /// Whether both minds are in Withdrawal, Sober or High regardless
+    /// of the actual value.
+    pub fn same_state(&self, other: &Mind) -> bool {
+        use self::Mind::*;
+        match (*self, *other) {
+            (Withdrawal(_), Withdrawal(_)) |
+            (Sober(_), Sober(_)) |
+            (High(_), High(_)) => true,
+            _ => false,
+        }
+    }
 }
 
 impl Display for Mind {
@@ -78,8 +99,16 @@ pub struct Player {
     pub mind: Mind,
     pub will: Ranged,
     pub tolerance: i32,
-    pub panic: Ranged,
-    pub stun: Ranged,
+    /// How many doses the player has used over the whole game.
+    pub doses_used: i32,
+    /// How many turns in a row the player hasn't been High.
+    pub clean_turns: i32,
+    /// Stun, panic and the other timed effects.
+    pub effects: StatusEffects,
+    /// Awarded for the sobriety milestones. Each one counts as an
+    /// extra point of Will when resisting doses and they're never
+    /// taken away.
+    pub sobriety_tokens: i32,
 
     pub pos: Point,
     pub inventory: Vec<Item>,
@@ -87,13 +116,25 @@ pub struct Player {
     // TODO: merge this with the other bonuses
     pub bonus: Bonus,
     pub bonuses: Vec<CompanionBonus>,
+    /// The NPC currently accompanying the player (if any).
+    pub companion: Option<MonsterId>,
     /// How many turns after max Will to achieve victory
     pub sobriety_counter: Ranged,
     pub current_high_streak: i32,
     pub longest_high_streak: i32,
+    /// Whether the player's last action was taken over by an
+    /// irresistible dose.
+    pub drawn_to_dose: bool,
+    /// The equipment the player carries. It's kept apart from the
+    /// `inventory` because it's never used up. Every kind of
+    /// equipment has its own slot so there's at most one of each.
+    pub equipment: Vec<Equipment>,
 
     pub dead: bool,
     pub invincible: bool,
+    /// The monster that killed the player. This is a copy because
+    /// the monster itself may not exist anymore, but its `id` still
+    /// refers to the one in the world.
     pub perpetrator: Option<Monster>,
 
     // TODO: Use a Ranged here?
@@ -102,29 +143,57 @@ pub struct Player {
 }
 
 impl Player {
-    pub fn new(pos: Point, invincible: bool) -> Player {
+    pub fn new(pos: Point, invincible: bool, balance: &Balance) -> Player {
         Player {
-            mind: Mind::Withdrawal(Ranged::new_max(WITHDRAWAL)),
-            will: Ranged::new(2, WILL),
+            mind: Mind::Withdrawal(Ranged::new_max(balance.withdrawal)),
+            will: Ranged::new(2, balance.will),
             tolerance: 0,
-            panic: Ranged::new_min(formula::PANIC_TURNS),
-            stun: Ranged::new_min(formula::STUN_TURNS),
+            doses_used: 0,
+            clean_turns: 0,
+            effects: StatusEffects::new(),
+            sobriety_tokens: 0,
             pos,
             inventory: vec![],
-            anxiety_counter: Ranged::new_min(ANXIETIES_PER_WILL),
+            anxiety_counter: Ranged::new_min(balance.anxieties_per_will),
             dead: false,
             invincible,
             perpetrator: None,
-            base_max_ap: formula::PLAYER_BASE_AP,
-            ap: formula::PLAYER_BASE_AP,
+            base_max_ap: balance.player_base_ap,
+            ap: balance.player_base_ap,
             bonus: Bonus::None,
             bonuses: Vec::with_capacity(10),
-            sobriety_counter: Ranged::new_min(SOBRIETY_COUNTER),
+            companion: None,
+            sobriety_counter: Ranged::new_min(balance.sobriety_counter),
             current_high_streak: 0,
             longest_high_streak: 0,
+            drawn_to_dose: false,
+            equipment: vec![],
+        }
+    }
+
+    pub fn has_equipment(&self, equipment: Equipment) -> bool {
+        self.equipment.contains(&equipment)
+    }
+
+    /// Put the equipment into its slot. Returns `false` if the slot
+    /// is taken already.
+    pub fn equip(&mut self, equipment: Equipment) -> bool {
+        if self.has_equipment(equipment) {
+            false
+        } else {
+            self.equipment.push(equipment);
+            true
         }
     }
 
+    /// How close the player can get to the `dose` before it pulls
+    /// them in.
+    pub fn resist_radius(&self, dose: &Item) -> i32 {
+        let radius =
+            formula::player_resist_radius(dose.irresistible, *self.will + self.sobriety_tokens);
+        self.effects.resist_radius(radius)
+    }
+
     pub fn move_to(&mut self, new_position: Point) {
         self.pos = new_position;
     }
@@ -142,17 +211,27 @@ impl Player {
         self.ap >= count
     }
 
-    pub fn new_turn(&mut self) {
+    pub fn new_turn(&mut self, balance: &Balance) {
         if self.alive() {
-            self.stun -= 1;
-            self.panic -= 1;
-
-            let mut mind_drop = formula::MIND_DROP_PER_TURN;
+            let mut mind_drop = balance.mind_drop_per_turn;
             if self.bonuses.contains(&CompanionBonus::HalveExhaustion) {
                 mind_drop /= 2;
             }
+            mind_drop = self.effects.mind_drop(self.mind, mind_drop);
+            // NOTE: the effects count down only after they've done
+            // their thing this turn. Otherwise the last turn would be
+            // lost.
+            self.effects.new_turn();
+
+            if self.mind.is_high() {
+                self.clean_turns = 0;
+            } else {
+                self.clean_turns += 1;
+                self.tolerance =
+                    formula::decay_tolerance(self.tolerance, self.clean_turns, balance);
+            }
 
-            self.mind = formula::mind_take_turn(self.mind, mind_drop);
+            self.mind = formula::mind_take_turn(self.mind, mind_drop, self.doses_used, balance);
             self.ap = self.max_ap();
         }
     }
@@ -169,7 +248,7 @@ impl Player {
         self.invincible || formula::cause_of_death(self).is_none()
     }
 
-    pub fn take_effect(&mut self, effect: Modifier) {
+    pub fn take_effect(&mut self, effect: Modifier, balance: &Balance) {
         use self::Modifier::*;
         match effect {
             Death => self.dead = true,
@@ -181,21 +260,34 @@ impl Player {
                 if !self.will.is_max() {
                     self.sobriety_counter.set_to_min();
                 }
-                self.mind = formula::process_hunger(self.mind, state_of_mind);
+                self.mind =
+                    formula::process_hunger(self.mind, state_of_mind, self.doses_used, balance);
             }
             Intoxication {
                 state_of_mind,
                 tolerance_increase,
             } => {
-                self.mind = formula::intoxicate(self.mind, self.tolerance, state_of_mind);
+                self.mind =
+                    formula::intoxicate(self.mind, self.tolerance, state_of_mind, balance);
                 self.tolerance += tolerance_increase;
+                self.doses_used += 1;
+                self.clean_turns = 0;
                 self.sobriety_counter.set_to_min();
             }
             Panic(turns) => {
-                self.panic += turns;
+                self.effects.add(status::Kind::Panic, turns, balance);
             }
             Stun(turns) => {
-                self.stun += turns;
+                self.effects.add(status::Kind::Stun, turns, balance);
+            }
+            Medication(turns) => {
+                self.effects.add(status::Kind::Medication, turns, balance);
+            }
+            DoseBlocker(turns) => {
+                self.effects.add(status::Kind::DoseBlocker, turns, balance);
+            }
+            SobrietyToken => {
+                self.sobriety_tokens += 1;
             }
         }
 
@@ -218,3 +310,40 @@ impl Render for Player {
         }
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::{Mind, Modifier, Player};
+    use formula::Balance;
+    use point::Point;
+
+    fn withdrawal(player: &Player) -> i32 {
+        match player.mind {
+            Mind::Withdrawal(value) => *value,
+            mind => panic!("Expected withdrawal, got: {:?}", mind),
+        }
+    }
+
+    /// How many turns the Mind doesn't drop for with the medication.
+    fn medicated_turns(medication: i32) -> i32 {
+        let balance = Balance::default();
+        let mut player = Player::new(Point::new(0, 0), false, &balance);
+        player.take_effect(Modifier::Medication(medication), &balance);
+        let mut turns = 0;
+        loop {
+            let before = withdrawal(&player);
+            player.new_turn(&balance);
+            if withdrawal(&player) < before {
+                return turns;
+            }
+            turns += 1;
+        }
+    }
+
+    #[test]
+    fn medication_lasts_exactly_its_turns() {
+        assert_eq!(medicated_turns(1), 1);
+        assert_eq!(medicated_turns(40), 40);
+    }
+}
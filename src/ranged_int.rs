@@ -14,7 +14,7 @@ use rand::Rng;
 // can easily create footguns (you "move" an iterator, then call iter
 // on the original nad it works but from the initial state). So we're
 // probably stuck with this instead of the nicer syntax. Oh well.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct InclusiveRange(pub i32, pub i32);
 
 impl InclusiveRange {
@@ -1,17 +1,21 @@
 
 
-use color::{self, Color};
-use engine::Draw;
+use blocker::Blocker;
+use color::{self, Color, Theme};
+use engine::{Draw, Settings};
 use formula;
-use game;
 use graphics;
-use item;
+use help::{self, HelpScreen};
+use keymap::{self, Action, GameAction, KeyBindings, MetaAction};
+use look;
 use monster;
+use pathfinding::Path;
 use player::{Bonus, CauseOfDeath, Mind};
 use point::{Point, SquareArea};
 use rect::Rectangle;
-use state::{Side, State};
+use state::{KeyBindingsScreen, Side, State};
 use std::borrow::Cow;
+use std::cmp;
 use std::collections::HashMap;
 
 use std::time::Duration;
@@ -19,7 +23,17 @@ use util;
 use world::Chunk;
 
 
-pub fn render_game(state: &State, dt: Duration, fps: i32, drawcalls: &mut Vec<Draw>) {
+/// How many of the latest messages fit at the bottom of the panel.
+const PANEL_MESSAGE_LINES: i32 = 8;
+
+
+pub fn render_game(
+    state: &State,
+    dt: Duration,
+    fps: i32,
+    settings: &Settings,
+    drawcalls: &mut Vec<Draw>,
+) {
     if state.player.alive() {
         let fade = formula::mind_fade_value(state.player.mind);
         if fade > 0.0 {
@@ -60,7 +74,6 @@ pub fn render_game(state: &State, dt: Duration, fps: i32, drawcalls: &mut Vec<Dr
     let world_size = state.world_size;
 
     let player_will_is_max = state.player.will.is_max();
-    let player_will = *state.player.will;
     // NOTE: this is here to appease the borrow checker. If we
     // borrowed the state here as immutable, we wouln't need it.
     let show_intoxication_effect = state.player.alive() && state.player.mind.is_high();
@@ -113,7 +126,7 @@ pub fn render_game(state: &State, dt: Duration, fps: i32, drawcalls: &mut Vec<Dr
         // Render the irresistible background of a dose
         for item in cell.items.iter() {
             if item.is_dose() && !player_will_is_max {
-                let resist_radius = formula::player_resist_radius(item.irresistible, player_will);
+                let resist_radius = state.player.resist_radius(item);
                 for point in SquareArea::new(world_pos, resist_radius) {
                     if in_fov(point) {
                         let screen_coords = screen_coords_from_world(point);
@@ -128,7 +141,25 @@ pub fn render_game(state: &State, dt: Duration, fps: i32, drawcalls: &mut Vec<Dr
             bonus == Bonus::UncoverMap
         {
             for item in cell.items.iter() {
-                graphics::draw(drawcalls, dt, display_pos, item);
+                let risk = if state.player.alive() && in_fov(world_pos) {
+                    formula::overdose_risk(
+                        state.player.mind,
+                        state.player.tolerance,
+                        item,
+                        &state.balance,
+                    )
+                } else {
+                    0.0
+                };
+                if risk > 0.0 {
+                    let color = overdose_blink_color(item.color, risk, total_time_ms);
+                    drawcalls.push(Draw::Char(display_pos, item.glyph, color));
+                } else {
+                    graphics::draw(drawcalls, dt, display_pos, item);
+                }
+            }
+            if let Some(ref equipment) = cell.equipment {
+                graphics::draw(drawcalls, dt, display_pos, equipment);
             }
         }
     }
@@ -175,6 +206,22 @@ pub fn render_game(state: &State, dt: Duration, fps: i32, drawcalls: &mut Vec<Dr
         }
     }
 
+    // NOTE: preview the path the player would travel on a click
+    if state.player_in_control() && state.travel.is_none() {
+        if let Some(destination) = state.mouse_travel_destination() {
+            let path = Path::find(
+                player_pos,
+                destination,
+                &state.world,
+                Blocker::WALL | Blocker::MONSTER,
+                player_pos,
+            );
+            for pos in path {
+                drawcalls.push(Draw::Background(screen_coords_from_world(pos), color::travel_path));
+            }
+        }
+    }
+
     // NOTE: render the player
     {
         let display_pos = screen_coords_from_world(state.player.pos);
@@ -186,26 +233,83 @@ pub fn render_game(state: &State, dt: Duration, fps: i32, drawcalls: &mut Vec<Dr
         state.panel_width,
         state.display_size,
         &state,
+        &settings.key_bindings,
         dt,
         drawcalls,
         fps,
     );
-    if state.show_keboard_movement_hints {
-        render_controls_help(state.map_size, drawcalls);
+    // NOTE: the tutorial explains the movement in its own prompt
+    if state.show_keboard_movement_hints && state.tutorial.is_none() {
+        render_controls_help(state.map_size, &settings.key_bindings, drawcalls);
+    }
+
+    if let Some(tutorial) = state.tutorial {
+        if state.player.alive() {
+            let prompt = tutorial.stage.prompt(&settings.key_bindings);
+            render_tutorial_prompt(state.map_size, &prompt, drawcalls);
+        }
     }
 
     if state.endgame_screen_visible {
-        render_endgame_screen(state, drawcalls);
+        render_endgame_screen(state, &settings.key_bindings, drawcalls);
+    }
+
+    if let Some(ref screen) = state.key_bindings_screen {
+        render_key_bindings_screen(state.display_size, screen, &settings.key_bindings, drawcalls);
+    }
+
+    if let Some(screen) = state.help_screen {
+        render_help_screen(state, screen, &settings.key_bindings, drawcalls);
+    }
+
+    if let Some(scroll) = state.message_log_screen {
+        render_message_log_screen(state, scroll, drawcalls);
+    }
+
+    if let Some(look_cursor) = state.look_cursor {
+        render_look_info(state, look_cursor, drawcalls);
+    } else {
+        let mouse_inside_map = state.mouse.tile_pos >= (0, 0) && state.mouse.tile_pos < state.map_size;
+        if mouse_inside_map && state.mouse.right {
+            let screen_left_top_corner = state.screen_position_in_world - (state.map_size / 2);
+            render_look_info(state, screen_left_top_corner + state.mouse.tile_pos, drawcalls);
+        }
     }
 
-    let mouse_inside_map = state.mouse.tile_pos >= (0, 0) && state.mouse.tile_pos < state.map_size;
-    if mouse_inside_map && state.mouse.right {
-        render_monster_info(state, drawcalls);
+    apply_theme(settings.color_theme, drawcalls);
+}
+
+
+/// Blink between the dose's colour and the white of the overdose.
+/// The riskier the dose, the faster it blinks.
+fn overdose_blink_color(item_color: Color, risk: f32, total_time_ms: i64) -> Color {
+    let cycle_ms = cmp::max(1, (500.0 / risk) as i64);
+    let progress = (total_time_ms % cycle_ms) as f32 / cycle_ms as f32;
+    // NOTE: go towards white in the first half of the cycle and back
+    // in the second one
+    let whiteness = 1.0 - (progress * 2.0 - 1.0).abs();
+    graphics::fade_color(item_color, color::overdose_animation, whiteness)
+}
+
+
+fn apply_theme(theme: Theme, drawcalls: &mut Vec<Draw>) {
+    if theme == Theme::Default {
+        return;
+    }
+    for drawcall in drawcalls.iter_mut() {
+        match *drawcall {
+            Draw::Char(_, _, ref mut color) |
+            Draw::Text(_, _, ref mut color) => *color = theme.foreground(*color),
+            Draw::Background(_, ref mut color) |
+            Draw::Rectangle(_, _, ref mut color) => *color = theme.background(*color),
+            // NOTE: the fades are full-screen effects, leave them be
+            Draw::Fade(..) => {}
+        }
     }
 }
 
 
-fn render_endgame_screen(state: &State, drawcalls: &mut Vec<Draw>) {
+fn render_endgame_screen(state: &State, key_bindings: &KeyBindings, drawcalls: &mut Vec<Draw>) {
     use self::CauseOfDeath::*;
     let cause_of_death = formula::cause_of_death(&state.player);
     let endgame_reason_text = if state.side == Side::Victory {
@@ -243,7 +347,19 @@ fn render_endgame_screen(state: &State, drawcalls: &mut Vec<Draw>) {
         "Longest High streak: {} turns",
         state.player.longest_high_streak
     );
-    let keyboard_text = "[N] New Game    [Q] Quit";
+    let key = |action| {
+        key_bindings
+            .keys(Action::Meta(action))
+            .first()
+            .map(|&key| keymap::key_name(key))
+            .unwrap_or("-".into())
+    };
+    let keyboard_text = format!(
+        "[{}] New Game    [{}] Help    [{}] Quit",
+        key(MetaAction::NewGame),
+        key(MetaAction::Help),
+        key(MetaAction::Quit)
+    );
 
     let longest_text = [
         endgame_reason_text,
@@ -251,19 +367,31 @@ fn render_endgame_screen(state: &State, drawcalls: &mut Vec<Draw>) {
         &turns_text,
         &carrying_doses_text,
         &high_streak_text,
-        keyboard_text,
+        &keyboard_text,
     ].iter()
         .map(|s| s.chars().count())
         .max()
         .unwrap() as i32;
-    let lines_count = 7;
+    // NOTE: make the box wide enough that the tip doesn't wrap
+    // into too many lines
+    let text_width = cmp::max(longest_text, 40);
+    let tip_lines = match state.death_tip {
+        Some(tip) => util::wrap_text(tip.text(), text_width as usize),
+        None => vec![],
+    };
+    let tip_y = 11;
+    let keyboard_y = if tip_lines.is_empty() {
+        13
+    } else {
+        tip_y + tip_lines.len() as i32 + 1
+    };
 
     let rect_dimensions = Point {
         // NOTE: 1 tile padding, which is why we have the `+ 2`.
-        x: longest_text + 2,
-        // NOTE: each line has an empty line below so we just have `+
-        // 1` for the top padding.
-        y: lines_count * 2 + 1,
+        x: text_width + 2,
+        // NOTE: the last line has an empty line below for the bottom
+        // padding.
+        y: keyboard_y + 2,
     };
     let rect_start = Point {
         x: (state.display_size.x - rect_dimensions.x) / 2,
@@ -333,11 +461,18 @@ fn render_endgame_screen(state: &State, drawcalls: &mut Vec<Draw>) {
         high_streak_text.into(),
         color::gui_text,
     ));
+    for (index, line) in tip_lines.into_iter().enumerate() {
+        drawcalls.push(Draw::Text(
+            rect_start + (centered_text_pos(rect_dimensions.x, &line), tip_y + index as i32),
+            line.into(),
+            color::gui_text,
+        ));
+    }
     drawcalls.push(Draw::Text(
         rect_start +
             (
                 centered_text_pos(rect_dimensions.x, &keyboard_text),
-                13,
+                keyboard_y,
             ),
         keyboard_text.into(),
         color::gui_text,
@@ -350,6 +485,7 @@ fn render_panel(
     width: i32,
     display_size: Point,
     state: &State,
+    key_bindings: &KeyBindings,
     dt: Duration,
     drawcalls: &mut Vec<Draw>,
     fps: i32,
@@ -384,6 +520,8 @@ fn render_panel(
         format!("Will: {}", *player.will).into(),
     ];
 
+    // NOTE: indices of the inventory lines with a risky dose
+    let mut overdose_warnings = vec![];
     if player.inventory.len() > 0 {
         lines.push("".into());
         lines.push("Inventory:".into());
@@ -394,12 +532,38 @@ fn render_panel(
             *count += 1;
         }
 
-        for kind in item::Kind::iter() {
-            if let Some(count) = item_counts.get(&kind) {
-                lines.push(
-                    format!("[{}] {:?}: {}", game::inventory_key(kind), kind, count).into(),
-                );
+        let item_archetypes = state.world.item_archetypes();
+        let mut kinds: Vec<_> = item_counts.keys().cloned().collect();
+        kinds.sort_by_key(|&kind| item_archetypes.archetype(kind).inventory_key);
+        for kind in kinds {
+            let archetype = item_archetypes.archetype(kind);
+            let action = Action::Game(GameAction::UseInventory(archetype.inventory_key));
+            let key = key_bindings
+                .keys(action)
+                .first()
+                .map(|&key| keymap::key_name(key))
+                .unwrap_or("-".into());
+            // NOTE: this is the one that gets used when the key's pressed
+            let next_item = player.inventory.iter().find(|item| item.kind == kind);
+            let overdose = next_item.map_or(false, |item| {
+                player.alive() &&
+                    formula::overdose_risk(player.mind, player.tolerance, item, &state.balance) > 0.0
+            });
+            if overdose {
+                overdose_warnings.push(lines.len());
             }
+            lines.push(
+                format!("[{}] {}: {}", key, archetype.name, item_counts[&kind])
+                    .into(),
+            );
+        }
+    }
+
+    if !player.equipment.is_empty() {
+        lines.push("".into());
+        lines.push("Equipment:".into());
+        for equipment in &player.equipment {
+            lines.push(format!("* {}", equipment.name()).into());
         }
     }
 
@@ -410,6 +574,9 @@ fn render_panel(
             format!("Sobriety: {}", player.sobriety_counter.percent()).into(),
         );
     }
+    if player.sobriety_tokens > 0 {
+        lines.push(format!("Tokens: {}", player.sobriety_tokens).into());
+    }
 
     if !player.bonuses.is_empty() {
         lines.push("Bonus:".into());
@@ -424,11 +591,8 @@ fn render_panel(
     }
 
     if player.alive() {
-        if *player.stun > 0 {
-            lines.push(format!("Stunned({})", *player.stun).into());
-        }
-        if *player.panic > 0 {
-            lines.push(format!("Panicking({})", *player.panic).into());
+        for effect in player.effects.active() {
+            lines.push(format!("{}({})", effect.kind.name(), effect.turns_left).into());
         }
     } else {
         lines.push("Dead".into());
@@ -464,6 +628,7 @@ fn render_panel(
     }
 
 
+    let lines_count = lines.len() as i32;
     for (y, line) in lines.into_iter().enumerate() {
         drawcalls.push(Draw::Text(
             Point {
@@ -475,6 +640,17 @@ fn render_panel(
         ));
     }
 
+    for y in overdose_warnings {
+        drawcalls.push(Draw::Text(
+            Point {
+                x: x + width - 2,
+                y: y as i32,
+            },
+            "!".into(),
+            color::gui_warning,
+        ));
+    }
+
     let max_val = match player.mind {
         Mind::Withdrawal(val) => val.max(),
         Mind::Sober(val) => val.max(),
@@ -515,73 +691,305 @@ fn render_panel(
         ));
     }
 
+    // NOTE: show the latest messages at the bottom, below everything
+    // else. The full history is on the message log screen.
+    let messages_bottom = if state.cheating { bottom - 2 } else { bottom };
+    let messages_top = cmp::max(lines_count + 1, messages_bottom - PANEL_MESSAGE_LINES + 1);
+    let message_lines = state.messages.lines(cmp::max(1, width - 2) as usize);
+    let visible_count = cmp::max(0, messages_bottom - messages_top + 1) as usize;
+    let skip = message_lines.len().saturating_sub(visible_count);
+    for (index, (line, message)) in message_lines.into_iter().skip(skip).enumerate() {
+        let color = if state.messages.is_new(&message) {
+            fg
+        } else {
+            color::gui_text_dim
+        };
+        drawcalls.push(Draw::Text(
+            Point::new(x + 1, messages_top + index as i32),
+            line.into(),
+            color,
+        ));
+    }
+}
+
+
+/// Show the tutorial's instructions at the top of the map.
+fn render_tutorial_prompt(map_size: Point, prompt: &str, drawcalls: &mut Vec<Draw>) {
+    let lines = util::wrap_text(prompt, cmp::max(1, map_size.x - 2) as usize);
+    drawcalls.push(Draw::Rectangle(
+        Point::new(0, 0),
+        Point::new(map_size.x, lines.len() as i32 + 2),
+        color::dim_background,
+    ));
+    for (index, line) in lines.into_iter().enumerate() {
+        drawcalls.push(Draw::Text(
+            Point::new(1, 1 + index as i32),
+            line.into(),
+            color::gui_text,
+        ));
+    }
 }
 
 
-fn render_monster_info(state: &State, drawcalls: &mut Vec<Draw>) {
-    let screen_left_top_corner = state.screen_position_in_world - (state.map_size / 2);
-    let mouse_world_pos = screen_left_top_corner + state.mouse.tile_pos;
-    // TODO: world.monster_on_pos is mutable, let's add an immutable version
-    let monster_area = Rectangle::from_point_and_size(
-        mouse_world_pos, (1, 1).into());
-    let mut debug_text = None;
-    for monster in state.world.monsters(monster_area) {
-        if monster.position == mouse_world_pos {
-            debug_text = Some(format!("{:#?}", monster));
-        }
+fn render_message_log_screen(state: &State, scroll: usize, drawcalls: &mut Vec<Draw>) {
+    let display_size = state.display_size;
+    drawcalls.push(Draw::Rectangle(
+        Point::new(0, 0),
+        display_size,
+        color::background,
+    ));
+
+    let padding = help::PADDING;
+    drawcalls.push(Draw::Text(
+        Point::new(padding, 1),
+        "Message log".into(),
+        color::gui_text,
+    ));
+
+    let lines = state.messages.lines(help::text_width(display_size));
+    if lines.is_empty() {
+        drawcalls.push(Draw::Text(
+            Point::new(padding, 3),
+            "Nothing happened yet.".into(),
+            color::gui_text_dim,
+        ));
     }
-    if mouse_world_pos == state.player.pos {
-        debug_text = Some(format!("{:#?}", state.player));
+    let visible_lines = lines.into_iter().skip(scroll).take(help::visible_lines(display_size));
+    for (index, (line, message)) in visible_lines.enumerate() {
+        let color = if state.messages.is_new(&message) {
+            color::gui_text
+        } else {
+            color::gui_text_dim
+        };
+        drawcalls.push(Draw::Text(Point::new(padding, 3 + index as i32), line.into(), color));
     }
 
-    if let Some(debug_text) = debug_text {
-        let height = debug_text.lines().count();
-        let width = debug_text.lines().map(|s| s.chars().count()).max().unwrap();
-        drawcalls.push(Draw::Rectangle(
-            (0, 0).into(),
-            (width as i32, height as i32).into(),
-            color::background,
+    drawcalls.push(Draw::Text(
+        Point::new(padding, display_size.y - 1),
+        "[Up/Down] Scroll  [Esc] Close".into(),
+        color::gui_text,
+    ));
+}
+
+
+/// Highlight the looked-at tile and show what's there.
+fn render_look_info(state: &State, pos: Point, drawcalls: &mut Vec<Draw>) {
+    let screen_left_top_corner = state.screen_position_in_world - (state.map_size / 2);
+    let cursor = pos - screen_left_top_corner;
+    drawcalls.push(Draw::Background(cursor, color::look_cursor));
+
+    let lines = look::describe(state, pos);
+    let height = lines.len() as i32;
+    let width = lines.iter().map(|s| s.chars().count()).max().unwrap_or(0) as i32;
+    // NOTE: keep the description out of the way of the cursor
+    let y = if cursor.y < state.map_size.y / 2 {
+        state.map_size.y - height
+    } else {
+        0
+    };
+    drawcalls.push(Draw::Rectangle(
+        Point::new(0, y),
+        Point::new(width, height),
+        color::dim_background,
+    ));
+    for (index, line) in lines.into_iter().enumerate() {
+        drawcalls.push(Draw::Text(
+            Point::new(0, y + index as i32),
+            line.into(),
+            color::gui_text,
         ));
-        for (index, line) in debug_text.lines().enumerate() {
-            drawcalls.push(Draw::Text(
-                Point {
-                    x: 0,
-                    y: 0 + index as i32,
-                },
-                line.to_string().into(),
-                color::gui_text
+    }
+}
+
+
+fn render_key_bindings_screen(
+    display_size: Point,
+    screen: &KeyBindingsScreen,
+    key_bindings: &KeyBindings,
+    drawcalls: &mut Vec<Draw>,
+) {
+    drawcalls.push(Draw::Rectangle(
+        Point::new(0, 0),
+        display_size,
+        color::background,
+    ));
+
+    let padding = 2;
+    let mut y = 1;
+    drawcalls.push(Draw::Text(
+        Point::new(padding, y),
+        "Key bindings".into(),
+        color::gui_text,
+    ));
+    y += 2;
+
+    let description_width = 24;
+    // NOTE: the title above and the message and controls below
+    let visible_actions = cmp::max(1, display_size.y - 9) as usize;
+    let first_visible = (screen.selected + 1).saturating_sub(visible_actions);
+    let actions = Action::all();
+    let visible = actions.iter().enumerate().skip(first_visible).take(visible_actions);
+    for (index, &action) in visible {
+        let selected = index == screen.selected;
+        if selected {
+            drawcalls.push(Draw::Rectangle(
+                Point::new(0, y),
+                Point::new(display_size.x, 1),
+                color::dim_background,
             ));
         }
+        drawcalls.push(Draw::Text(
+            Point::new(padding, y),
+            action.description().into(),
+            color::gui_text,
+        ));
+        let keys_text = if selected && screen.waiting_for_key {
+            "Press a key (Esc to cancel)".into()
+        } else {
+            key_bindings
+                .keys(action)
+                .into_iter()
+                .map(keymap::key_name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        drawcalls.push(Draw::Text(
+            Point::new(padding + description_width, y),
+            keys_text.into(),
+            color::gui_text,
+        ));
+        y += 1;
+    }
+
+    y += 1;
+    if let Some(ref message) = screen.message {
+        drawcalls.push(Draw::Text(
+            Point::new(padding, y),
+            message.clone().into(),
+            color::gui_warning,
+        ));
+    }
+    y += 2;
+    drawcalls.push(Draw::Text(
+        Point::new(padding, y),
+        "[Up/Down] Select  [Enter] Add a key".into(),
+        color::gui_text,
+    ));
+    drawcalls.push(Draw::Text(
+        Point::new(padding, y + 1),
+        "[C] Clear  [R] Reset all  [Esc] Close".into(),
+        color::gui_text,
+    ));
+}
+
+
+fn render_help_screen(
+    state: &State,
+    screen: HelpScreen,
+    key_bindings: &KeyBindings,
+    drawcalls: &mut Vec<Draw>,
+) {
+    let display_size = state.display_size;
+    drawcalls.push(Draw::Rectangle(
+        Point::new(0, 0),
+        display_size,
+        color::background,
+    ));
+
+    let padding = help::PADDING;
+    let pages = [help::Page::Controls, help::Page::Legend, help::Page::Tips];
+    let page_number = pages.iter().position(|&page| page == screen.page).unwrap_or(0) + 1;
+    drawcalls.push(Draw::Text(
+        Point::new(padding, 1),
+        format!("Help: {} ({}/{})", screen.page.title(), page_number, pages.len()).into(),
+        color::gui_text,
+    ));
+
+    let text_width = help::text_width(display_size);
+    let lines = help::lines(screen.page, state, key_bindings, text_width);
+    let description_width = 24;
+    let mut y = 3;
+    for line in lines.into_iter().skip(screen.scroll).take(help::visible_lines(display_size)) {
+        match line {
+            help::Line::Empty => {}
+            help::Line::Text(text) => {
+                drawcalls.push(Draw::Text(Point::new(padding, y), text.into(), color::gui_text));
+            }
+            help::Line::Binding(description, keys) => {
+                drawcalls.push(Draw::Text(
+                    Point::new(padding, y),
+                    description.into(),
+                    color::gui_text,
+                ));
+                drawcalls.push(Draw::Text(
+                    Point::new(padding + description_width, y),
+                    keys.into(),
+                    color::gui_text,
+                ));
+            }
+            help::Line::Glyph(glyph, glyph_color, description) => {
+                drawcalls.push(Draw::Char(Point::new(padding, y), glyph, glyph_color));
+                drawcalls.push(Draw::Text(
+                    Point::new(padding + 2, y),
+                    description.into(),
+                    color::gui_text,
+                ));
+            }
+        }
+        y += 1;
     }
+
+    drawcalls.push(Draw::Text(
+        Point::new(padding, display_size.y - 1),
+        "[Left/Right] Page  [Up/Down] Scroll  [Esc] Close".into(),
+        color::gui_text,
+    ));
 }
 
 
-fn render_controls_help(map_size: Point, drawcalls: &mut Vec<Draw>) {
-    fn rect_dim(lines: &[&str]) -> (i32, i32) {
+fn render_controls_help(map_size: Point, key_bindings: &KeyBindings, drawcalls: &mut Vec<Draw>) {
+    fn rect_dim(lines: &[String]) -> (i32, i32) {
         (
-            lines.iter().map(|l| l.len() as i32).max().unwrap(),
+            lines.iter().map(|l| l.len() as i32).max().unwrap_or(0),
             lines.len() as i32,
         )
     }
 
-    fn draw_rect(lines: &[&'static str], start: Point, w: i32, h: i32, drawcalls: &mut Vec<Draw>) {
+    fn draw_rect(lines: &[String], start: Point, w: i32, h: i32, drawcalls: &mut Vec<Draw>) {
+        // NOTE: the action might not be bound to anything
+        if lines.is_empty() {
+            return;
+        }
         drawcalls.push(Draw::Rectangle(
             start,
             Point::new(w, h),
             color::dim_background,
         ));
-        for (index, &line) in lines.iter().enumerate() {
+        for (index, line) in lines.iter().enumerate() {
             drawcalls.push(Draw::Text(
                 start + Point::new(0, index as i32),
-                line.into(),
+                line.clone().into(),
                 color::gui_text,
             ));
         }
     };
 
+    let hint_lines = |action: GameAction| {
+        let mut lines: Vec<String> = key_bindings
+            .keys(Action::Game(action))
+            .into_iter()
+            .map(keymap::key_name)
+            .collect();
+        if lines.len() > 1 {
+            let last = lines.len() - 1;
+            lines[last] = format!("or: {}", lines[last]);
+        }
+        lines
+    };
+
     let padding = 3;
 
-    let lines = ["Up", "Num 8", "or: K"];
+    let lines = hint_lines(GameAction::N);
     let (width, height) = rect_dim(&lines);
     let start = Point {
         x: (map_size.x - width) / 2,
@@ -589,7 +997,7 @@ fn render_controls_help(map_size: Point, drawcalls: &mut Vec<Draw>) {
     };
     draw_rect(&lines, start, width, height, drawcalls);
 
-    let lines = ["Down", "Num 2", "or: J"];
+    let lines = hint_lines(GameAction::S);
     let (width, height) = rect_dim(&lines);
     let start = Point {
         x: (map_size.x - width) / 2,
@@ -597,7 +1005,7 @@ fn render_controls_help(map_size: Point, drawcalls: &mut Vec<Draw>) {
     };
     draw_rect(&lines, start, width, height, drawcalls);
 
-    let lines = ["Left", "Num 4", "or: H"];
+    let lines = hint_lines(GameAction::W);
     let (width, height) = rect_dim(&lines);
     let start = Point {
         x: padding,
@@ -605,7 +1013,7 @@ fn render_controls_help(map_size: Point, drawcalls: &mut Vec<Draw>) {
     };
     draw_rect(&lines, start, width, height, drawcalls);
 
-    let lines = ["Right", "Num 6", "or: L"];
+    let lines = hint_lines(GameAction::E);
     let (width, height) = rect_dim(&lines);
     let start = Point {
         x: map_size.x - width - padding,
@@ -613,7 +1021,7 @@ fn render_controls_help(map_size: Point, drawcalls: &mut Vec<Draw>) {
     };
     draw_rect(&lines, start, width, height, drawcalls);
 
-    let lines = ["Shift+Right", "Num 7", "or: Y"];
+    let lines = hint_lines(GameAction::NW);
     let (width, height) = rect_dim(&lines);
     let start = Point {
         x: padding,
@@ -621,7 +1029,7 @@ fn render_controls_help(map_size: Point, drawcalls: &mut Vec<Draw>) {
     };
     draw_rect(&lines, start, width, height, drawcalls);
 
-    let lines = ["Shift+Right", "Num 9", "or: U"];
+    let lines = hint_lines(GameAction::NE);
     let (width, height) = rect_dim(&lines);
     let start = Point {
         x: map_size.x - width - padding,
@@ -629,7 +1037,7 @@ fn render_controls_help(map_size: Point, drawcalls: &mut Vec<Draw>) {
     };
     draw_rect(&lines, start, width, height, drawcalls);
 
-    let lines = ["Ctrl+Left", "Num 1", "or: N"];
+    let lines = hint_lines(GameAction::SW);
     let (width, height) = rect_dim(&lines);
     let start = Point {
         x: padding,
@@ -637,7 +1045,7 @@ fn render_controls_help(map_size: Point, drawcalls: &mut Vec<Draw>) {
     };
     draw_rect(&lines, start, width, height, drawcalls);
 
-    let lines = ["Ctrl+Right", "Num 3", "or: M"];
+    let lines = hint_lines(GameAction::SE);
     let (width, height) = rect_dim(&lines);
     let start = Point {
         x: map_size.x - width - padding,
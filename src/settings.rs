@@ -0,0 +1,168 @@
+use color::Theme;
+use formula::{self, Balance};
+use keymap::KeyBindings;
+
+use serde_json;
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+
+/// The player's preferences. They're read from the settings file at
+/// startup and written back whenever they change in the game.
+///
+/// Things such as the fullscreen/windowed display, font, controls,
+/// etc. These are carried by the engine and passed to `game::update`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub fullscreen: bool,
+    /// The size of the game map in tiles. The map is a square.
+    ///
+    /// It can't be so big that the view reaches past the simulation
+    /// area (see `formula::max_map_size`), there would be nothing
+    /// generated there.
+    pub map_size: i32,
+    /// The width of the in-game status panel in tiles.
+    pub panel_width: i32,
+    /// A tilemap image to use instead of the built-in font. It must
+    /// have the same layout and tile size as the built-in one.
+    pub font: Option<PathBuf>,
+    pub key_bindings: KeyBindings,
+    pub color_theme: Theme,
+    pub record_replays: bool,
+    /// The maximum number of turns the rest command waits for.
+    pub rest_turns: i32,
+
+    /// Where the settings are saved. `None` means they're not
+    /// persisted at all.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            fullscreen: false,
+            // NOTE: at our current font, the height of 43 is the
+            // maximum value for 1336x768 monitors.
+            map_size: 43,
+            panel_width: 20,
+            font: None,
+            key_bindings: KeyBindings::default(),
+            color_theme: Theme::Default,
+            record_replays: true,
+            rest_turns: 20,
+            path: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings from `path`. If there's no file there, the
+    /// default settings are used and written out so the player can
+    /// edit them.
+    pub fn load(path: PathBuf) -> Self {
+        let mut settings = if path.exists() {
+            let mut contents = String::new();
+            File::open(&path)
+                .and_then(|mut file| file.read_to_string(&mut contents))
+                .expect(&format!("Could not read the settings file: '{}'.", path.display()));
+            serde_json::from_str(&contents).unwrap_or_else(|err| {
+                panic!("Could not parse the settings file '{}': {}", path.display(), err)
+            })
+        } else {
+            Settings::default()
+        };
+        settings.path = Some(path);
+
+        // NOTE: the map size is limited by the simulation radius.
+        let balance = Balance::load();
+        if let Err(message) = settings.validate(&balance) {
+            panic!("Invalid settings in '{}': {}", settings.path.as_ref().unwrap().display(), message);
+        }
+
+        settings.save();
+        settings
+    }
+
+    fn validate(&self, balance: &Balance) -> Result<(), String> {
+        // NOTE: the map is centered on the player
+        if self.map_size < 3 {
+            return Err(format!("map_size must be at least 3, got {}", self.map_size));
+        }
+        let max_map_size = formula::max_map_size(balance);
+        if self.map_size > max_map_size {
+            return Err(format!(
+                "map_size must be at most {} (with the simulation_radius of {}), got {}",
+                max_map_size,
+                balance.simulation_radius,
+                self.map_size
+            ));
+        }
+        if self.panel_width < 0 {
+            return Err(format!("panel_width must not be negative, got {}", self.panel_width));
+        }
+        if self.rest_turns < 1 {
+            return Err(format!("rest_turns must be at least 1, got {}", self.rest_turns));
+        }
+        self.key_bindings.validate()?;
+        Ok(())
+    }
+
+    /// Write the settings out. Failing to do so is not fatal, the
+    /// game just won't remember the changes.
+    pub fn save(&self) {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return,
+        };
+        let json = serde_json::to_string_pretty(self).expect("Could not serialise the settings.");
+        let result = match path.parent() {
+            Some(directory) => fs::create_dir_all(directory),
+            None => Ok(()),
+        }.and_then(|_| File::create(path))
+            .and_then(|mut file| file.write_all(json.as_bytes()));
+        if let Err(err) = result {
+            println!("Could not save the settings to '{}': {}", path.display(), err);
+        }
+    }
+}
+
+
+/// The location of the settings file in the platform's config
+/// directory.
+pub fn default_path() -> Option<PathBuf> {
+    config_directory().map(|dir| dir.join("dose-response").join(SETTINGS_FILE_NAME))
+}
+
+#[cfg(target_os = "windows")]
+fn config_directory() -> Option<PathBuf> {
+    env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn config_directory() -> Option<PathBuf> {
+    home_directory().map(|home| home.join("Library").join("Application Support"))
+}
+
+#[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
+fn config_directory() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(ref dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => home_directory().map(|home| home.join(".config")),
+    }
+}
+
+/// `$HOME`. We don't use `env::home_dir` because it's deprecated.
+#[cfg(not(target_os = "windows"))]
+fn home_directory() -> Option<PathBuf> {
+    match env::var_os("HOME") {
+        Some(ref dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => None,
+    }
+}
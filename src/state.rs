@@ -1,12 +1,19 @@
 use animation::{AreaOfEffect, ScreenFade};
+use blocker::Blocker;
+use data::{ItemArchetypes, MonsterArchetypes};
 use engine::Mouse;
+use formula::{self, Balance};
+use generators::Generator;
+use help::HelpScreen;
+use item;
 use keys::Keys;
+use messages::MessageLog;
 use monster;
-use player::Player;
+use player::{Mind, Player};
 use point::Point;
 use util;
 use rand::{IsaacRng, SeedableRng};
-
+use rect::Rectangle;
 use stats::Stats;
 use std::collections::VecDeque;
 use std::fs::{self, File};
@@ -14,6 +21,8 @@ use std::io::{self, BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use timer::Timer;
+use tips::Tip;
+use tutorial::Tutorial;
 use world::World;
 
 
@@ -41,11 +50,64 @@ pub enum Command {
     NW,
     SE,
     SW,
-    UseFood,
-    UseDose,
-    UseCardinalDose,
-    UseDiagonalDose,
-    UseStrongDose,
+    UseItem(item::Kind),
+    /// Pass the turn without doing anything.
+    Wait,
+}
+
+
+/// Where the player is travelling to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TravelGoal {
+    /// A specific tile (e.g. one the player clicked on).
+    Destination(Point),
+    /// The nearest unexplored place.
+    Explore,
+    /// The item at the given position. The travel ends when it's
+    /// gone.
+    Item(Point),
+    /// Bump into the NPC to make them the player's companion.
+    Companion(monster::MonsterId),
+}
+
+
+/// The player walking somewhere on their own. They take one step
+/// each turn until they get there or something interrupts them.
+#[derive(Clone, Debug)]
+pub struct Travel {
+    pub goal: TravelGoal,
+    /// The monsters the player could already see when they set out.
+    /// Any other monster showing up stops the travel.
+    pub known_monsters: Vec<monster::MonsterId>,
+    /// Positions of the items the player could already see.
+    pub known_items: Vec<Point>,
+    /// The player's Mind when they set out.
+    pub mind: Mind,
+}
+
+
+/// The player passing turns until something happens.
+#[derive(Clone, Debug)]
+pub struct Rest {
+    pub turns_left: i32,
+    /// The monsters the player could already see when they started
+    /// resting. Any other monster showing up stops the rest.
+    pub known_monsters: Vec<monster::MonsterId>,
+    /// The player started resting while stunned or panicking so they
+    /// want to stop once that's over.
+    pub until_recovered: bool,
+}
+
+
+/// The screen for changing the key bindings.
+#[derive(Clone, Debug, Default)]
+pub struct KeyBindingsScreen {
+    /// Index into `keymap::Action::all()`.
+    pub selected: usize,
+    /// The next key pressed will be bound to the selected action.
+    pub waiting_for_key: bool,
+    /// Why the last change couldn't be made (e.g. the key is taken).
+    pub message: Option<String>,
 }
 
 
@@ -71,12 +133,29 @@ pub fn generate_replay_path() -> Option<PathBuf> {
     None
 }
 
+/// Directory where the chunks far away from the player are stored
+/// during the game.
+#[cfg(not(feature = "web"))]
+pub fn generate_chunk_cache_path(seed: u32) -> Option<PathBuf> {
+    use std::env;
+    // NOTE: the random suffix makes sure that two games with the same
+    // seed (e.g. a game and its replay) don't share their chunks.
+    let cache_name = format!("chunks-{}-{}", seed, util::random_seed());
+    Some(env::temp_dir().join("dose-response").join(cache_name))
+}
+
+#[cfg(feature = "web")]
+pub fn generate_chunk_cache_path(_seed: u32) -> Option<PathBuf> {
+    None
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Verification {
     pub turn: i32,
     pub chunk_count: usize,
     pub player_pos: Point,
-    pub monsters: Vec<(Point, Point, monster::Kind)>,
+    /// Monster ID, position, position of its chunk and kind.
+    pub monsters: Vec<(monster::MonsterId, Point, Point, monster::Kind)>,
 }
 
 pub struct State {
@@ -88,6 +167,7 @@ pub struct State {
     pub world_size: Point,
     pub chunk_size: i32,
     pub world: World,
+    pub balance: Balance,
 
     /// The size of the game map inside the game window. We're keeping
     /// this square so this value repesents both width and heigh.
@@ -107,6 +187,8 @@ pub struct State {
     pub keys: Keys,
     pub mouse: Mouse,
     pub commands: VecDeque<Command>,
+    /// The last few commands the player executed, oldest first.
+    pub recent_commands: VecDeque<Command>,
     pub verifications: VecDeque<Verification>,
     pub command_logger: Box<Write>,
     pub side: Side,
@@ -129,6 +211,34 @@ pub struct State {
     pub endgame_screen_visible: bool,
 
     pub show_keboard_movement_hints: bool,
+
+    /// The key bindings screen takes over the keyboard while it's open.
+    pub key_bindings_screen: Option<KeyBindingsScreen>,
+
+    /// The help screen takes over the keyboard while it's open.
+    pub help_screen: Option<HelpScreen>,
+
+    /// The message log screen takes over the keyboard while it's
+    /// open. This is the index of the first line shown.
+    pub message_log_screen: Option<usize>,
+
+    /// The world position of the look mode cursor. The look mode
+    /// takes over the keyboard while it's on.
+    pub look_cursor: Option<Point>,
+
+    pub travel: Option<Travel>,
+    pub rest: Option<Rest>,
+
+    /// The tip shown on the endgame screen.
+    pub death_tip: Option<Tip>,
+    /// All the tips shown so far, oldest first. These carry over to
+    /// the next game so the player doesn't see the same one again.
+    pub shown_tips: Vec<Tip>,
+
+    pub messages: MessageLog,
+
+    /// Set when playing the tutorial rather than a regular game.
+    pub tutorial: Option<Tutorial>,
 }
 
 impl State {
@@ -141,26 +251,50 @@ impl State {
         verifications: VecDeque<Verification>,
         log_writer: W,
         seed: u32,
+        balance: Balance,
+        monster_archetypes: MonsterArchetypes,
+        item_archetypes: ItemArchetypes,
         cheating: bool,
         invincible: bool,
         replay: bool,
         replay_full_speed: bool,
         exit_after: bool,
+        generator: Generator,
     ) -> State {
         let seed_arr: &[_] = &[seed];
         let world_centre = (0, 0).into();
         assert_eq!(world_size.x, world_size.y);
         assert_eq!(display_size, (map_size + panel_width, map_size));
+        // NOTE: a replay can come with a smaller `simulation_radius`
+        // than the one the settings were checked against.
+        assert!(
+            map_size <= formula::max_map_size(&balance),
+            "The map_size of {} is too big for the simulation_radius of {}.",
+            map_size,
+            balance.simulation_radius
+        );
         let player_position = world_centre;
         let mut rng: IsaacRng = SeedableRng::from_seed(seed_arr);
-        let world = World::new(&mut rng, seed, world_size.x, 32, player_position);
+        let world = World::new(
+            &mut rng,
+            seed,
+            world_size.x,
+            32,
+            player_position,
+            generator,
+            generate_chunk_cache_path(seed),
+            monster_archetypes,
+            item_archetypes,
+            &balance,
+        );
 
         State {
-            player: Player::new(player_position, invincible),
+            player: Player::new(player_position, invincible, &balance),
             explosion_animation: None,
             chunk_size: 32,
             world_size,
             world,
+            balance,
             map_size: (map_size, map_size).into(),
             panel_width,
             display_size,
@@ -170,6 +304,7 @@ impl State {
             keys: Keys::new(),
             mouse: Default::default(),
             commands,
+            recent_commands: VecDeque::new(),
             verifications,
             command_logger: Box::new(log_writer),
             side: Side::Player,
@@ -188,6 +323,19 @@ impl State {
             screen_fading: None,
             endgame_screen_visible: false,
             show_keboard_movement_hints: true,
+            key_bindings_screen: None,
+            help_screen: None,
+            message_log_screen: None,
+            look_cursor: None,
+            travel: None,
+            rest: None,
+            death_tip: None,
+            shown_tips: vec![],
+            messages: MessageLog::new(),
+            tutorial: match generator {
+                Generator::Forrest => None,
+                Generator::Tutorial => Some(Tutorial::new()),
+            },
         }
     }
 
@@ -222,7 +370,12 @@ Reason: '{}'.",
             Box::new(io::sink())
         };
 
+        let balance = Balance::load();
+        let monster_archetypes = MonsterArchetypes::load();
+        let item_archetypes = ItemArchetypes::load();
         log_seed(&mut writer, seed);
+        log_balance(&mut writer, &balance);
+        log_archetypes(&mut writer, &monster_archetypes, &item_archetypes);
         let cheating = false;
         let replay = false;
         let replay_full_speed = false;
@@ -235,11 +388,56 @@ Reason: '{}'.",
             verifications,
             writer,
             seed,
+            balance,
+            monster_archetypes,
+            item_archetypes,
             cheating,
             invincible,
             replay,
             replay_full_speed,
             exit_after,
+            Generator::Forrest,
+        )
+    }
+
+    /// Start the tutorial: a small hand-authored map with prompts
+    /// telling the player what to do.
+    pub fn new_tutorial(
+        world_size: Point,
+        map_size: i32,
+        panel_width: i32,
+        display_size: Point,
+        exit_after: bool,
+        invincible: bool,
+    ) -> State {
+        // NOTE: the tutorial isn't recorded. The replays don't know
+        // about the tutorial map and we'd get out of sync.
+        let writer = io::sink();
+        let seed = util::random_seed();
+        let balance = Balance::load();
+        let monster_archetypes = MonsterArchetypes::load();
+        let item_archetypes = ItemArchetypes::load();
+        let cheating = false;
+        let replay = false;
+        let replay_full_speed = false;
+        State::new(
+            world_size,
+            map_size,
+            panel_width,
+            display_size,
+            VecDeque::new(),
+            VecDeque::new(),
+            writer,
+            seed,
+            balance,
+            monster_archetypes,
+            item_archetypes,
+            cheating,
+            invincible,
+            replay,
+            replay_full_speed,
+            exit_after,
+            Generator::Tutorial,
         )
     }
 
@@ -257,6 +455,9 @@ Reason: '{}'.",
         let mut commands = VecDeque::new();
         let mut verifications = VecDeque::new();
         let seed: u32;
+        let balance: Balance;
+        let monster_archetypes: MonsterArchetypes;
+        let item_archetypes: ItemArchetypes;
         match File::open(replay_path) {
             Ok(file) => {
                 let mut lines = BufReader::new(file).lines();
@@ -270,6 +471,51 @@ Reason: '{}'.",
                     None => panic!("The replay file is empty."),
                 }
 
+                match lines.next() {
+                    Some(Ok(line)) => {
+                        balance = serde_json::from_str(&line).expect(&format!(
+                            "Couldn't load the balance configuration: '{}'.",
+                            line
+                        ));
+                    }
+                    Some(Err(err)) => {
+                        panic!("Error reading a line from the replay file: {:?}.", err)
+                    }
+                    None => panic!("The replay file is missing the balance configuration."),
+                }
+
+                match lines.next() {
+                    Some(Ok(line)) => {
+                        monster_archetypes = serde_json::from_str(&line).expect(&format!(
+                            "Couldn't load the monster archetypes: '{}'.",
+                            line
+                        ));
+                    }
+                    Some(Err(err)) => {
+                        panic!("Error reading a line from the replay file: {:?}.", err)
+                    }
+                    None => panic!("The replay file is missing the monster archetypes."),
+                }
+                if let Err(message) = monster_archetypes.validate() {
+                    panic!("Invalid monster archetypes in the replay: {}", message);
+                }
+
+                match lines.next() {
+                    Some(Ok(line)) => {
+                        item_archetypes = serde_json::from_str(&line).expect(&format!(
+                            "Couldn't load the item archetypes: '{}'.",
+                            line
+                        ));
+                    }
+                    Some(Err(err)) => {
+                        panic!("Error reading a line from the replay file: {:?}.", err)
+                    }
+                    None => panic!("The replay file is missing the item archetypes."),
+                }
+                if let Err(message) = item_archetypes.validate() {
+                    panic!("Invalid item archetypes in the replay: {}", message);
+                }
+
                 loop {
                     match lines.next() {
                         Some(Ok(line)) => {
@@ -319,11 +565,15 @@ Reason: '{}'.",
             verifications,
             Box::new(io::sink()),
             seed,
+            balance,
+            monster_archetypes,
+            item_archetypes,
             cheating,
             invincible,
             replay,
             replay_full_speed,
             exit_after,
+            Generator::Forrest,
         )
     }
 
@@ -334,21 +584,59 @@ Reason: '{}'.",
         for &chunk_pos in &chunks {
             for monster in self.world.chunk(chunk_pos).unwrap().monsters() {
                 if !monster.dead {
-                    monsters.push((monster.position, chunk_pos, monster.kind));
+                    monsters.push((monster.id, monster.position, chunk_pos, monster.kind));
                 }
             }
         }
-        monsters.sort_by_key(|&(monster_pos, _chunk_pos, kind)| {
-            (monster_pos.x, monster_pos.y, kind)
-        });
+        monsters.sort_by_key(|&(id, _monster_pos, _chunk_pos, _kind)| id);
 
         Verification {
             turn: self.turn,
-            chunk_count: chunks.len(),
+            chunk_count: self.world.chunk_count(),
             player_pos: self.player.pos,
             monsters,
         }
     }
+
+    /// The world position of the tile under the mouse cursor if the
+    /// player can travel there: it's on the map, explored and not
+    /// blocked.
+    pub fn mouse_travel_destination(&self) -> Option<Point> {
+        let tile_pos = self.mouse.tile_pos;
+        if !(tile_pos >= (0, 0) && tile_pos < self.map_size) {
+            return None;
+        }
+        let screen_left_top_corner = self.screen_position_in_world - (self.map_size / 2);
+        let pos = screen_left_top_corner + tile_pos;
+        let explored = self.world.cell(pos).map_or(false, |cell| cell.explored);
+        if explored && self.world.walkable(pos, Blocker::WALL, self.player.pos) {
+            Some(pos)
+        } else {
+            None
+        }
+    }
+
+    /// The area around the player where the world is fully
+    /// simulated.
+    pub fn simulation_area(&self) -> Rectangle {
+        Rectangle::center(self.player.pos, Point::from_i32(self.balance.simulation_radius))
+    }
+
+    /// The area around the player where the chunks stay loaded. The
+    /// world outside of the `simulation_area` is only simulated
+    /// roughly here.
+    pub fn loaded_area(&self) -> Rectangle {
+        Rectangle::center(self.player.pos, Point::from_i32(self.balance.simulation_radius * 2))
+    }
+
+    /// Whether the player is in control right now, i.e. they're not
+    /// watching a replay or looking at some other screen.
+    pub fn player_in_control(&self) -> bool {
+        !self.replay && self.player.alive() && !self.endgame_screen_visible &&
+            self.key_bindings_screen.is_none() && self.help_screen.is_none() &&
+            self.message_log_screen.is_none() && self.look_cursor.is_none() &&
+            self.side != Side::Victory
+    }
 }
 
 
@@ -356,6 +644,39 @@ pub fn log_seed<W: Write>(writer: &mut W, seed: u32) {
     writeln!(writer, "{}", seed).unwrap();
 }
 
+/// Store the balance the game is played with. Replays must use
+/// exactly the same values to stay in sync.
+pub fn log_balance<W: Write>(writer: &mut W, balance: &Balance) {
+    use serde_json;
+    let json = serde_json::to_string(balance).expect(&format!(
+        "Could not serialise {:?} to json.",
+        balance
+    ));
+    writeln!(writer, "{}", json).unwrap();
+}
+
+/// Store the monster and item archetypes the game is played with.
+/// The commands refer to the items by their index and the generated
+/// world depends on both so they must not change under a replay.
+pub fn log_archetypes<W: Write>(
+    writer: &mut W,
+    monster_archetypes: &MonsterArchetypes,
+    item_archetypes: &ItemArchetypes,
+) {
+    use serde_json;
+    let monsters = serde_json::to_string(monster_archetypes)
+        .expect("Could not serialise the monster archetypes to json.");
+    writeln!(writer, "{}", monsters).unwrap();
+    let items = serde_json::to_string(item_archetypes)
+        .expect("Could not serialise the item archetypes to json.");
+    writeln!(writer, "{}", items).unwrap();
+}
+
+/// How many of the last executed commands `State::recent_commands`
+/// keeps.
+pub const RECENT_COMMANDS_COUNT: usize = 10;
+
+
 pub fn log_command<W: Write>(writer: &mut W, command: Command) {
     use serde_json;
     let json_command = serde_json::to_string(&command).expect(&format!(
@@ -0,0 +1,245 @@
+use formula::Balance;
+use player::Mind;
+use std::cmp;
+
+
+/// The timed effects the player can be under.
+///
+/// Everything an effect does is described by the methods below. To
+/// add a new one, add it here and fill in the ones that apply to it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// The player can't move.
+    Stun,
+    /// The player moves in a random direction.
+    Panic,
+    /// The Mind doesn't drop during the withdrawal.
+    Medication,
+    /// Doses can't pull the player in.
+    DoseBlocker,
+}
+
+/// What happens when the player gets an effect they're already
+/// under.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Stacking {
+    /// The durations add up.
+    AddDuration,
+    /// The longer of the two durations is kept.
+    KeepLongest,
+}
+
+/// How an effect overrides the action the player picked.
+///
+/// When several effects want to change the action, the one further
+/// down this list wins.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, PartialOrd, Ord)]
+pub enum ActionFilter {
+    /// Move to a random neighbouring tile instead.
+    RandomDirection,
+    /// Don't go anywhere, just lose the turn.
+    StayInPlace,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Stun => "Stunned",
+            Kind::Panic => "Panicking",
+            Kind::Medication => "Medicated",
+            Kind::DoseBlocker => "Doses blocked",
+        }
+    }
+
+    pub fn stacking(self) -> Stacking {
+        match self {
+            Kind::Stun | Kind::Panic | Kind::Medication => Stacking::AddDuration,
+            Kind::DoseBlocker => Stacking::KeepLongest,
+        }
+    }
+
+    /// The longest the effect can last.
+    pub fn max_turns(self, balance: &Balance) -> i32 {
+        match self {
+            Kind::Stun => balance.stun_turns.1,
+            Kind::Panic => balance.panic_turns.1,
+            Kind::Medication => balance.medication_turns.1,
+            Kind::DoseBlocker => balance.dose_blocker_turns.1,
+        }
+    }
+
+    pub fn action_filter(self) -> Option<ActionFilter> {
+        match self {
+            Kind::Stun => Some(ActionFilter::StayInPlace),
+            Kind::Panic => Some(ActionFilter::RandomDirection),
+            Kind::Medication | Kind::DoseBlocker => None,
+        }
+    }
+
+    /// Change how much the Mind drops this turn.
+    pub fn mind_drop(self, mind: Mind, drop: i32) -> i32 {
+        match (self, mind) {
+            (Kind::Medication, Mind::Withdrawal(_)) => 0,
+            _ => drop,
+        }
+    }
+
+    /// Change how close the player can get to a dose before it pulls
+    /// them in.
+    pub fn resist_radius(self, radius: i32) -> i32 {
+        match self {
+            Kind::DoseBlocker => 0,
+            _ => radius,
+        }
+    }
+}
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Effect {
+    pub kind: Kind,
+    pub turns_left: i32,
+}
+
+
+/// The effects the player is under right now, in the order they got
+/// them. Each kind is there at most once.
+#[derive(Clone, Debug, Default)]
+pub struct StatusEffects {
+    effects: Vec<Effect>,
+}
+
+impl StatusEffects {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add(&mut self, kind: Kind, turns: i32, balance: &Balance) {
+        let max_turns = kind.max_turns(balance);
+        if let Some(effect) = self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            let turns = match kind.stacking() {
+                Stacking::AddDuration => effect.turns_left + turns,
+                Stacking::KeepLongest => cmp::max(effect.turns_left, turns),
+            };
+            effect.turns_left = cmp::min(turns, max_turns);
+            return;
+        }
+        let turns_left = cmp::min(turns, max_turns);
+        if turns_left > 0 {
+            self.effects.push(Effect { kind, turns_left });
+        }
+    }
+
+    /// How many more turns the effect lasts. Zero if the player isn't
+    /// under it.
+    pub fn turns_left(&self, kind: Kind) -> i32 {
+        self.effects
+            .iter()
+            .find(|effect| effect.kind == kind)
+            .map_or(0, |effect| effect.turns_left)
+    }
+
+    pub fn has(&self, kind: Kind) -> bool {
+        self.turns_left(kind) > 0
+    }
+
+    pub fn active(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Count a turn down on every effect and drop the ones that have
+    /// run out.
+    pub fn new_turn(&mut self) {
+        for effect in &mut self.effects {
+            effect.turns_left -= 1;
+        }
+        self.effects.retain(|effect| effect.turns_left > 0);
+    }
+
+    /// How the effects override the player's action, if at all.
+    pub fn action_filter(&self) -> Option<ActionFilter> {
+        self.effects
+            .iter()
+            .filter_map(|effect| effect.kind.action_filter())
+            .max()
+    }
+
+    pub fn mind_drop(&self, mind: Mind, drop: i32) -> i32 {
+        self.effects
+            .iter()
+            .fold(drop, |drop, effect| effect.kind.mind_drop(mind, drop))
+    }
+
+    pub fn resist_radius(&self, radius: i32) -> i32 {
+        self.effects
+            .iter()
+            .fold(radius, |radius, effect| effect.kind.resist_radius(radius))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{ActionFilter, Kind, StatusEffects};
+    use formula::Balance;
+    use player::Mind;
+    use ranged_int::Ranged;
+
+    #[test]
+    fn effects_wear_off() {
+        let balance = Balance::default();
+        let mut effects = StatusEffects::new();
+        effects.add(Kind::Stun, 2, &balance);
+        assert_eq!(effects.turns_left(Kind::Stun), 2);
+        effects.new_turn();
+        assert!(effects.has(Kind::Stun));
+        effects.new_turn();
+        assert!(!effects.has(Kind::Stun));
+        assert!(effects.active().is_empty());
+    }
+
+    #[test]
+    fn stacking_rules() {
+        let balance = Balance::default();
+        let mut effects = StatusEffects::new();
+        effects.add(Kind::Panic, 3, &balance);
+        effects.add(Kind::Panic, 2, &balance);
+        assert_eq!(effects.turns_left(Kind::Panic), 5);
+        effects.add(Kind::DoseBlocker, 30, &balance);
+        effects.add(Kind::DoseBlocker, 20, &balance);
+        assert_eq!(effects.turns_left(Kind::DoseBlocker), 30);
+        assert_eq!(effects.active().len(), 2);
+    }
+
+    #[test]
+    fn duration_is_capped() {
+        let balance = Balance::default();
+        let mut effects = StatusEffects::new();
+        effects.add(Kind::Stun, 1000, &balance);
+        assert_eq!(effects.turns_left(Kind::Stun), balance.stun_turns.1);
+        effects.add(Kind::Stun, 1000, &balance);
+        assert_eq!(effects.turns_left(Kind::Stun), balance.stun_turns.1);
+    }
+
+    #[test]
+    fn stun_overrides_panic() {
+        let balance = Balance::default();
+        let mut effects = StatusEffects::new();
+        assert_eq!(effects.action_filter(), None);
+        effects.add(Kind::Panic, 3, &balance);
+        assert_eq!(effects.action_filter(), Some(ActionFilter::RandomDirection));
+        effects.add(Kind::Stun, 3, &balance);
+        assert_eq!(effects.action_filter(), Some(ActionFilter::StayInPlace));
+    }
+
+    #[test]
+    fn medication_only_helps_with_withdrawal() {
+        let balance = Balance::default();
+        let mut effects = StatusEffects::new();
+        effects.add(Kind::Medication, 10, &balance);
+        let withdrawal = Mind::Withdrawal(Ranged::new_max(balance.withdrawal));
+        let sober = Mind::Sober(Ranged::new_max(balance.sober));
+        assert_eq!(effects.mind_drop(withdrawal, 2), 0);
+        assert_eq!(effects.mind_drop(sober, 2), 2);
+    }
+}
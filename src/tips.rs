@@ -0,0 +1,280 @@
+use data::ItemArchetypes;
+use formula;
+use monster;
+use player::{CauseOfDeath, Player};
+use point::Point;
+use rect::Rectangle;
+use state::{Command, State};
+use status;
+
+
+/// How far from the player do we look for monsters that swarmed them.
+const SWARM_RADIUS: i32 = 2;
+
+/// Any death earlier than this is considered early in the game.
+const EARLY_GAME_TURNS: i32 = 300;
+
+
+/// Advice shown on the endgame screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tip {
+    DoseWhileHigh,
+    DrawnToDose,
+    RiskyDosesBlink,
+    AnxietyDrainsWill,
+    HungerCausesWithdrawal,
+    HungerHuntsInPacks,
+    DepressionIsFast,
+    Stunned,
+    Panicking,
+    WaitingDoesntHelp,
+    ToleranceGrows,
+    CarryFood,
+    NoPeopleWhileHigh,
+}
+
+impl Tip {
+    pub fn text(self) -> &'static str {
+        use self::Tip::*;
+        match self {
+            DoseWhileHigh => "Using another dose when High will likely cause overdose early on.",
+            DrawnToDose => "Doses pull you in when you get too close. The more Will you have, the closer you can get.",
+            RiskyDosesBlink => "A dose that would make you overdose blinks white. The faster it blinks, the worse it is.",
+            AnxietyDrainsWill => "Being hit by `a` reduces your Will. You lose when it reaches zero.",
+            HungerCausesWithdrawal => "Being hit by `h` will quickly get you into a withdrawal.",
+            HungerHuntsInPacks => "`h` hunt in packs. Don't let them surround you.",
+            DepressionIsFast => "`D` moves twice as fast as you do and kills you with a single hit. Keep away from it.",
+            Stunned => "You can't move while stunned. Avoid `v` when there's other danger around.",
+            Panicking => "You run around randomly while panicking. Avoid `S` when there's other danger around.",
+            WaitingDoesntHelp => "Waiting doesn't stop the withdrawal. Go look for food or a dose instead.",
+            ToleranceGrows => "Every dose raises your tolerance so the same doses get you less High.",
+            CarryFood => "Carry food with you. Eating it keeps the withdrawal at bay for a while.",
+            NoPeopleWhileHigh => "People won't talk to you while you're High.",
+        }
+    }
+}
+
+
+/// Everything the tips are chosen by.
+pub struct Death<'a> {
+    pub player: &'a Player,
+    pub turn: i32,
+    /// The last commands the player issued, oldest first.
+    pub recent_commands: Vec<Command>,
+    /// How many of the `recent_commands` used a dose.
+    pub recent_doses: usize,
+    /// The kinds of the monsters right next to the player.
+    pub nearby_monsters: Vec<monster::Kind>,
+}
+
+impl<'a> Death<'a> {
+    pub fn new(state: &'a State) -> Self {
+        let recent_commands: Vec<Command> = state.recent_commands.iter().cloned().collect();
+        let recent_doses = count_doses(&recent_commands, state.world.item_archetypes());
+        let area = Rectangle::center(state.player.pos, Point::from_i32(SWARM_RADIUS));
+        let nearby_monsters = state
+            .world
+            .monsters(area)
+            .filter(|monster| monster.alive())
+            .map(|monster| monster.kind)
+            .collect();
+        Death {
+            player: &state.player,
+            turn: state.turn,
+            recent_commands,
+            recent_doses,
+            nearby_monsters,
+        }
+    }
+}
+
+fn count_doses(commands: &[Command], item_archetypes: &ItemArchetypes) -> usize {
+    commands
+        .iter()
+        .filter(|command| match **command {
+            Command::UseItem(kind) => item_archetypes.item(kind).is_dose(),
+            _ => false,
+        })
+        .count()
+}
+
+
+/// All the tips that apply to the death, the most relevant first.
+pub fn relevant_tips(death: &Death) -> Vec<Tip> {
+    use self::CauseOfDeath::*;
+    use self::Tip::*;
+    let player = death.player;
+    let perpetrator = player.perpetrator.as_ref().map(|monster| monster.kind);
+    let mut tips = vec![];
+
+    if player.drawn_to_dose {
+        tips.push(DrawnToDose);
+    }
+    if player.effects.has(status::Kind::Stun) {
+        tips.push(Stunned);
+    }
+    if player.effects.has(status::Kind::Panic) {
+        tips.push(Panicking);
+    }
+
+    match formula::cause_of_death(player) {
+        Some(Overdosed) => {
+            if death.recent_doses > 0 && death.turn < EARLY_GAME_TURNS {
+                tips.push(DoseWhileHigh);
+            }
+            tips.push(RiskyDosesBlink);
+        }
+        Some(LostWill) => {
+            if perpetrator == Some(monster::Kind::Anxiety) {
+                tips.push(AnxietyDrainsWill);
+            }
+        }
+        Some(Exhausted) => {
+            let hunger_count = death
+                .nearby_monsters
+                .iter()
+                .filter(|&&kind| kind == monster::Kind::Hunger)
+                .count();
+            if hunger_count > 1 {
+                tips.push(HungerHuntsInPacks);
+            }
+            if perpetrator == Some(monster::Kind::Hunger) {
+                tips.push(HungerCausesWithdrawal);
+            }
+            let waiting = death
+                .recent_commands
+                .iter()
+                .filter(|command| match **command {
+                    Command::Wait => true,
+                    _ => false,
+                })
+                .count();
+            if waiting * 2 > death.recent_commands.len() {
+                tips.push(WaitingDoesntHelp);
+            }
+            if player.tolerance > 0 {
+                tips.push(ToleranceGrows);
+            }
+            tips.push(CarryFood);
+        }
+        Some(Killed) => {
+            if perpetrator == Some(monster::Kind::Depression) {
+                tips.push(DepressionIsFast);
+            }
+        }
+        None => {}
+    }
+
+    // NOTE: general tips for when nothing specific applies (or the
+    // player's seen them all already)
+    for &tip in &[CarryFood, NoPeopleWhileHigh, RiskyDosesBlink, ToleranceGrows] {
+        if !tips.contains(&tip) {
+            tips.push(tip);
+        }
+    }
+
+    tips
+}
+
+
+/// Pick the most relevant tip the player hasn't seen yet. If they've
+/// seen them all, pick the one they saw the longest time ago.
+///
+/// `shown` lists the tips shown so far, oldest first.
+pub fn pick(relevant: &[Tip], shown: &[Tip]) -> Option<Tip> {
+    let unseen = relevant.iter().find(|&tip| !shown.contains(tip));
+    if let Some(&tip) = unseen {
+        return Some(tip);
+    }
+    relevant.iter().cloned().min_by_key(|tip| {
+        shown.iter().rposition(|shown_tip| shown_tip == tip)
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{pick, relevant_tips, Death, Tip};
+    use formula::Balance;
+    use monster::Kind;
+    use player::{Mind, Player};
+    use ranged_int::Ranged;
+    use state::Command;
+    use status;
+
+    fn death(player: &Player) -> Death {
+        Death {
+            player,
+            turn: 100,
+            recent_commands: vec![],
+            recent_doses: 0,
+            nearby_monsters: vec![],
+        }
+    }
+
+    #[test]
+    fn early_overdose_warns_about_doses_while_high() {
+        let balance = Balance::default();
+        let mut player = Player::new((0, 0).into(), false, &balance);
+        player.mind = Mind::High(Ranged::new_max(balance.high));
+        let mut death = death(&player);
+        death.recent_commands = vec![Command::N, Command::UseItem(::item::Kind(0))];
+        death.recent_doses = 1;
+        assert_eq!(relevant_tips(&death)[0], Tip::DoseWhileHigh);
+    }
+
+    #[test]
+    fn being_stunned_comes_first() {
+        let balance = Balance::default();
+        let mut player = Player::new((0, 0).into(), false, &balance);
+        player.mind = Mind::Withdrawal(Ranged::new_min(balance.withdrawal));
+        player.effects.add(status::Kind::Stun, 3, &balance);
+        assert_eq!(relevant_tips(&death(&player))[0], Tip::Stunned);
+    }
+
+    #[test]
+    fn swarmed_by_hunger() {
+        let balance = Balance::default();
+        let mut player = Player::new((0, 0).into(), false, &balance);
+        player.mind = Mind::Withdrawal(Ranged::new_min(balance.withdrawal));
+        let mut death = death(&player);
+        death.nearby_monsters = vec![Kind::Hunger, Kind::Anxiety, Kind::Hunger];
+        assert!(relevant_tips(&death).contains(&Tip::HungerHuntsInPacks));
+    }
+
+    #[test]
+    fn exhausted_while_waiting() {
+        let balance = Balance::default();
+        let mut player = Player::new((0, 0).into(), false, &balance);
+        player.mind = Mind::Withdrawal(Ranged::new_min(balance.withdrawal));
+        let mut death = death(&player);
+        death.recent_commands = vec![Command::Wait, Command::Wait, Command::N];
+        assert_eq!(relevant_tips(&death)[0], Tip::WaitingDoesntHelp);
+    }
+
+    #[test]
+    fn there_is_always_a_tip() {
+        let balance = Balance::default();
+        let player = Player::new((0, 0).into(), false, &balance);
+        assert!(!relevant_tips(&death(&player)).is_empty());
+    }
+
+    #[test]
+    fn pick_skips_the_seen_tips() {
+        let relevant = [Tip::Stunned, Tip::CarryFood];
+        assert_eq!(pick(&relevant, &[]), Some(Tip::Stunned));
+        assert_eq!(pick(&relevant, &[Tip::Stunned]), Some(Tip::CarryFood));
+    }
+
+    #[test]
+    fn pick_repeats_the_oldest_tip_when_all_were_seen() {
+        let relevant = [Tip::Stunned, Tip::CarryFood];
+        let shown = [Tip::CarryFood, Tip::Stunned];
+        assert_eq!(pick(&relevant, &shown), Some(Tip::CarryFood));
+    }
+
+    #[test]
+    fn pick_without_relevant_tips() {
+        assert_eq!(pick(&[], &[Tip::Stunned]), None);
+    }
+}
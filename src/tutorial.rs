@@ -0,0 +1,265 @@
+use blocker::Blocker;
+use color;
+use keymap::{self, Action, GameAction, KeyBindings, MetaAction};
+use messages::{Event, Message};
+use monster::{self, CompanionBonus};
+use point::{Point, SquareArea};
+use state::{Command, State};
+use std::cmp;
+
+
+/// How far from the player can the tutorial monsters show up.
+const SPAWN_DISTANCE: i32 = 3;
+
+
+/// The steps of the tutorial, in order. Each one waits for the
+/// player to do something before moving on to the next.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stage {
+    MoveDiagonally,
+    EatFood,
+    PickUpDose,
+    DefeatAnxiety,
+    RecruitCompanion,
+    Done,
+}
+
+impl Stage {
+    pub fn next(self) -> Self {
+        use self::Stage::*;
+        match self {
+            MoveDiagonally => EatFood,
+            EatFood => PickUpDose,
+            PickUpDose => DefeatAnxiety,
+            DefeatAnxiety => RecruitCompanion,
+            RecruitCompanion => Done,
+            Done => Done,
+        }
+    }
+
+    /// What the player should do now. The keys are the ones they
+    /// have bound.
+    pub fn prompt(self, key_bindings: &KeyBindings) -> String {
+        use self::Stage::*;
+        let game = |action| keys(key_bindings, Action::Game(action));
+        let meta = |action| keys(key_bindings, Action::Meta(action));
+        match self {
+            MoveDiagonally => format!(
+                "Welcome! Move with {}, {}, {} and {}. Try moving diagonally too: {}, {}, {} and {}.",
+                game(GameAction::N),
+                game(GameAction::W),
+                game(GameAction::S),
+                game(GameAction::E),
+                game(GameAction::NW),
+                game(GameAction::NE),
+                game(GameAction::SW),
+                game(GameAction::SE)
+            ),
+            EatFood => format!(
+                "You're in withdrawal and it gets worse every turn. Walk over the food (`%`) to pick it up and press {} to eat it.",
+                game(GameAction::Eat)
+            ),
+            PickUpDose => "Doses (`i`) get you High. Go east and pick up the dose there, but don't use it yet. Save it for when there's no food around.".into(),
+            DefeatAnxiety => "That `a` is Anxiety. It drains your Will when it hits you. Walk into it to defeat it. Defeating enough of them makes your Will grow.".into(),
+            RecruitCompanion => "People (`@`) can accompany you and help you out. Walk into them while you're Sober. They won't talk to you while you're High.".into(),
+            Done => format!(
+                "That's all! Press {} for help or {} to start a real game.",
+                meta(MetaAction::Help),
+                meta(MetaAction::NewGame)
+            ),
+        }
+    }
+}
+
+
+/// All the keys bound to the `action`, e.g. "`Up`/`Num 8`/`K`".
+fn keys(key_bindings: &KeyBindings, action: Action) -> String {
+    let keys = key_bindings
+        .keys(action)
+        .into_iter()
+        .map(|key| format!("`{}`", keymap::key_name(key)))
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        "`-`".into()
+    } else {
+        keys.join("/")
+    }
+}
+
+
+/// Keeps track of the tutorial progress. It sits on top of the
+/// regular game and watches what the player does.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tutorial {
+    pub stage: Stage,
+    /// How many messages from the log did we check already.
+    seen_messages: usize,
+}
+
+impl Tutorial {
+    pub fn new() -> Self {
+        Tutorial {
+            stage: Stage::MoveDiagonally,
+            seen_messages: 0,
+        }
+    }
+}
+
+
+/// Move the tutorial along if the player did what the current stage
+/// asked for.
+pub fn update(state: &mut State) {
+    let mut tutorial = match state.tutorial {
+        Some(tutorial) => tutorial,
+        None => return,
+    };
+    let completed = {
+        let messages = state.messages.messages();
+        let seen = cmp::min(tutorial.seen_messages, messages.len());
+        tutorial.seen_messages = messages.len();
+        stage_completed(tutorial.stage, state, &messages[seen..])
+    };
+    if completed {
+        tutorial.stage = tutorial.stage.next();
+        enter_stage(tutorial.stage, state);
+    }
+    state.tutorial = Some(tutorial);
+}
+
+
+fn stage_completed(stage: Stage, state: &State, new_messages: &[Message]) -> bool {
+    let happened = |event: Event| new_messages.iter().any(|message| message.event == event);
+    match stage {
+        Stage::MoveDiagonally => match state.recent_commands.back() {
+            Some(&Command::NE) | Some(&Command::NW) | Some(&Command::SE) | Some(&Command::SW) => true,
+            _ => false,
+        },
+        Stage::EatFood => happened(Event::AteFood),
+        Stage::PickUpDose => {
+            state.player.inventory.iter().any(|item| item.is_dose()) ||
+                happened(Event::DoseTookEffect)
+        }
+        Stage::DefeatAnxiety => happened(Event::Defeated(monster::Kind::Anxiety)),
+        Stage::RecruitCompanion => state.player.companion.is_some(),
+        Stage::Done => false,
+    }
+}
+
+
+/// Set up the world for the stage: bring in the monsters the player
+/// needs to deal with.
+fn enter_stage(stage: Stage, state: &mut State) {
+    match stage {
+        Stage::DefeatAnxiety => {
+            spawn_near_player(state, monster::Kind::Anxiety);
+        }
+        Stage::RecruitCompanion => {
+            if let Some(id) = spawn_near_player(state, monster::Kind::Npc) {
+                if let Some(npc) = state.world.monster_mut(id) {
+                    npc.companion_bonus = Some(CompanionBonus::DoubleWillGrowth);
+                    npc.color = color::npc_will;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+
+/// Place the monster close to the player, but not right next to
+/// them.
+fn spawn_near_player(state: &mut State, kind: monster::Kind) -> Option<monster::MonsterId> {
+    let player_pos = state.player.pos;
+    let candidates: Vec<Point> = SquareArea::new(player_pos, SPAWN_DISTANCE + 1)
+        .filter(|&pos| player_pos.tile_distance(pos) > 1)
+        .collect();
+    let blocker = Blocker::PLAYER | Blocker::MONSTER | Blocker::WALL;
+    let pos = candidates
+        .into_iter()
+        .find(|&pos| state.world.walkable(pos, blocker, player_pos));
+    pos.and_then(|pos| state.world.spawn_monster(kind, pos, player_pos))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{enter_stage, stage_completed, Stage, SPAWN_DISTANCE};
+    use keymap::KeyBindings;
+    use messages::{Event, Message};
+    use monster::{self, CompanionBonus};
+    use point::Point;
+    use rect::Rectangle;
+    use state::{Command, State};
+
+    fn tutorial_state() -> State {
+        let world_size = (1_073_741_824, 1_073_741_824).into();
+        State::new_tutorial(world_size, 43, 20, (63, 43).into(), false, false)
+    }
+
+    fn monsters_near_player(state: &State, kind: monster::Kind) -> Vec<monster::MonsterId> {
+        let area = Rectangle::center(state.player.pos, Point::from_i32(SPAWN_DISTANCE + 1));
+        state.world.monsters(area).filter(|m| m.kind == kind).map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn diagonal_step_completes_the_first_stage() {
+        let mut state = tutorial_state();
+        assert!(!stage_completed(Stage::MoveDiagonally, &state, &[]));
+        state.recent_commands.push_back(Command::N);
+        assert!(!stage_completed(Stage::MoveDiagonally, &state, &[]));
+        state.recent_commands.push_back(Command::SE);
+        assert!(stage_completed(Stage::MoveDiagonally, &state, &[]));
+    }
+
+    #[test]
+    fn only_new_messages_count() {
+        let state = tutorial_state();
+        let ate = [Message { turn: 1, event: Event::AteFood }];
+        let attacked = [Message { turn: 1, event: Event::Attacked(monster::Kind::Anxiety) }];
+        assert!(stage_completed(Stage::EatFood, &state, &ate));
+        assert!(!stage_completed(Stage::EatFood, &state, &attacked));
+        assert!(!stage_completed(Stage::EatFood, &state, &[]));
+    }
+
+    #[test]
+    fn done_is_never_completed() {
+        let state = tutorial_state();
+        let ate = [Message { turn: 1, event: Event::AteFood }];
+        assert!(!stage_completed(Stage::Done, &state, &ate));
+    }
+
+    #[test]
+    fn entering_the_anxiety_stage_spawns_one() {
+        let mut state = tutorial_state();
+        assert!(monsters_near_player(&state, monster::Kind::Anxiety).is_empty());
+        enter_stage(Stage::DefeatAnxiety, &mut state);
+        assert_eq!(monsters_near_player(&state, monster::Kind::Anxiety).len(), 1);
+    }
+
+    #[test]
+    fn entering_the_companion_stage_spawns_a_helpful_npc() {
+        let mut state = tutorial_state();
+        enter_stage(Stage::RecruitCompanion, &mut state);
+        let npcs = monsters_near_player(&state, monster::Kind::Npc);
+        assert_eq!(npcs.len(), 1);
+        let npc = state.world.monster(npcs[0]).unwrap();
+        assert_eq!(npc.companion_bonus, Some(CompanionBonus::DoubleWillGrowth));
+    }
+
+    #[test]
+    fn prompts_use_the_bound_keys() {
+        let bindings = KeyBindings::default();
+        assert!(Stage::EatFood.prompt(&bindings).contains("`E`"));
+        assert!(Stage::MoveDiagonally.prompt(&bindings).contains("`Y`"));
+        assert!(Stage::Done.prompt(&bindings).contains("`F5`"));
+    }
+
+    #[test]
+    fn stages_end_with_done() {
+        let mut stage = Stage::MoveDiagonally;
+        for _ in 0..10 {
+            stage = stage.next();
+        }
+        assert_eq!(stage, Stage::Done);
+    }
+}
@@ -30,6 +30,29 @@ pub fn num_microseconds(duration: Duration) -> Option<u64> {
 }
 
 
+/// Split the text into lines at most `width` characters long. Words
+/// longer than that get a line of their own.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let line_length = line.chars().count();
+        if line_length > 0 && line_length + 1 + word.chars().count() > width {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+
 #[cfg(not(feature = "web"))]
 pub fn random_seed() -> u32 {
     rand::random::<u32>()
@@ -42,3 +65,30 @@ pub fn random_seed() -> u32 {
     let random_float = unsafe { ::random() };
     (random_float * ::std::u32::MAX as f32) as u32
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::wrap_text;
+
+    #[test]
+    fn wrap_at_word_boundaries() {
+        assert_eq!(
+            wrap_text("Carry food with you.", 10),
+            vec!["Carry food", "with you."]
+        );
+    }
+
+    #[test]
+    fn long_words_get_their_own_line() {
+        assert_eq!(
+            wrap_text("a withdrawal b", 5),
+            vec!["a", "withdrawal", "b"]
+        );
+    }
+
+    #[test]
+    fn wrap_empty_text() {
+        assert!(wrap_text("", 10).is_empty());
+    }
+}
@@ -1,16 +1,21 @@
 use blocker::Blocker;
-use formula;
-use generators::{self, GeneratedWorld};
-use item::{self, Item};
+use data::{ItemArchetypes, MonsterArchetypes};
+use formula::Balance;
+use generators::{self, GeneratedWorld, Generator};
+use item::{self, Equipment, Item};
 use level::{self, Cell, Level};
-use monster::Monster;
+use monster::{self, Monster, MonsterId};
 use player;
 use point::{CircularArea, Point, SquareArea};
 
 use rand::{IsaacRng, Rng, SeedableRng};
 use ranged_int::InclusiveRange;
 use rect::Rectangle;
-use std::collections::HashMap;
+use serde_json;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
 
 pub struct Chunk {
     position: Point,
@@ -20,26 +25,38 @@ pub struct Chunk {
 }
 
 impl Chunk {
-    fn new(world_seed: u32, position: ChunkPosition, size: i32, player_position: Point) -> Self {
+    fn new(
+        world_seed: u32,
+        position: ChunkPosition,
+        size: i32,
+        player_position: Point,
+        generator: Generator,
+        monster_archetypes: &MonsterArchetypes,
+        item_archetypes: &ItemArchetypes,
+    ) -> Self {
         let pos = position.position;
-        // NOTE: `x` and `y` overflow on negative values here, but all
-        // we care about is having a distinct value for each position
-        // so our seeds don't repeat. So this is fine here.
-        let chunk_seed: &[_] = &[world_seed, pos.x as u32, pos.y as u32];
-
-        // TODO: Monsters in different chunks will now have identical
-        // IDs. We need to investigate whether that's a problem.
-
         let mut chunk = Chunk {
             position: pos,
-            rng: SeedableRng::from_seed(chunk_seed),
+            rng: Chunk::seeded_rng(world_seed, pos),
             level: Level::new(size, size),
             monsters: vec![],
         };
 
         let mut one_off_rng = chunk.rng.clone();
-        let generated_data =
-            generators::forrest::generate(&mut chunk.rng, &mut one_off_rng, chunk.level.size(), player_position);
+        let area = Rectangle::from_point_and_size(pos, chunk.level.size());
+        let generated_data = match generator {
+            Generator::Forrest => generators::forrest::generate(
+                &mut chunk.rng,
+                &mut one_off_rng,
+                monster_archetypes,
+                item_archetypes,
+                area,
+                player_position,
+            ),
+            Generator::Tutorial => {
+                generators::tutorial::generate(item_archetypes, area, player_position)
+            }
+        };
 
         chunk.populate(generated_data);
 
@@ -47,37 +64,111 @@ impl Chunk {
     }
 
 
+    fn seeded_rng(world_seed: u32, pos: Point) -> IsaacRng {
+        // NOTE: `x` and `y` overflow on negative values here, but all
+        // we care about is having a distinct value for each position
+        // so our seeds don't repeat. So this is fine here.
+        let chunk_seed: &[_] = &[world_seed, pos.x as u32, pos.y as u32];
+        SeedableRng::from_seed(chunk_seed)
+    }
+
+    /// Restore a chunk previously stored with `into_serialized`.
+    fn from_serialized(world_seed: u32, serialized: SerializedChunk) -> Self {
+        let pos = serialized.position;
+        let size = serialized.size;
+        // NOTE: the rng is only used for the chunk generation so it
+        // doesn't matter it won't be in the same state as when the
+        // chunk was stored.
+        let mut chunk = Chunk {
+            position: pos,
+            rng: Chunk::seeded_rng(world_seed, pos),
+            level: Level::from_cells(size, size, serialized.cells),
+            monsters: vec![],
+        };
+        for monster in serialized.monsters.into_iter() {
+            chunk.add_monster(monster);
+        }
+        chunk
+    }
+
+    /// Consume the chunk and return everything that needs to be
+    /// stored in order to restore it later.
+    fn into_serialized(self) -> SerializedChunk {
+        let size = self.level.size();
+        assert_eq!(size.x, size.y);
+        SerializedChunk {
+            position: self.position,
+            size: size.x,
+            cells: self.level.into_cells(),
+            // NOTE: dead monsters are kept around only so the
+            // indices in `monsters` don't change. We don't need them
+            // once the chunk is stored.
+            monsters: self.monsters.into_iter().filter(Monster::alive).collect(),
+        }
+    }
+
     fn populate(&mut self, generated_world: GeneratedWorld) {
-        let (map, generated_monsters, items) = generated_world;
+        let (map, generated_monsters, items, equipment) = generated_world;
         for &(pos, item) in map.iter() {
-            let pos = self.level.level_position(pos);
+            let pos = self.level_position(pos);
             self.level.set_tile(pos, item);
         }
-        for (index, mut monster) in generated_monsters.into_iter().enumerate() {
-            // TODO: the pos conversion would not be necessary if the
-            // worldgen operated with world positions in the first
-            // place.
-            let pos = self.level.level_position(monster.position);
-            assert!(self.level.walkable(pos, Blocker::WALL | Blocker::MONSTER));
-            monster.position = self.world_position(pos);
-            self.monsters.push(monster);
-            self.level.set_monster(pos, index);
-            assert!(!self.level.walkable(pos, Blocker::WALL | Blocker::MONSTER));
+        for monster in generated_monsters.into_iter() {
+            self.add_monster(monster);
         }
         for &(pos, item) in items.iter() {
-            let pos = self.level.level_position(pos);
+            let pos = self.level_position(pos);
             assert!(self.level.walkable(pos, Blocker::WALL));
             self.level.add_item(pos, item);
         }
+        for &(pos, equipment) in equipment.iter() {
+            let pos = self.level_position(pos);
+            assert!(self.level.walkable(pos, Blocker::WALL));
+            self.level.cell_mut(pos).equipment = Some(equipment);
+        }
     }
 
-    pub fn level_position(&self, world_position: Point) -> level::LevelPosition {
-        self.level.level_position(world_position - self.position)
+    /// Place the monster into the chunk and return its index into
+    /// `monsters`. Panics if the monster's position is occupied.
+    fn add_monster(&mut self, monster: Monster) -> usize {
+        let pos = self.level_position(monster.position);
+        assert!(self.level.walkable(pos, Blocker::WALL | Blocker::MONSTER));
+        let index = self.monsters.len();
+        self.monsters.push(monster);
+        self.level.set_monster(pos, index);
+        assert!(!self.level.walkable(pos, Blocker::WALL | Blocker::MONSTER));
+        index
+    }
+
+    /// Remove the monster at the given position from the chunk
+    /// entirely (rather than just marking it dead).
+    ///
+    /// The last monster in `monsters` takes the place of the removed
+    /// one. If that happens, its ID and new index are returned as
+    /// well so the caller can update any references to it.
+    fn take_monster(&mut self, pos: level::LevelPosition) -> Option<(Monster, Option<(MonsterId, usize)>)> {
+        let index = match self.level.monsters.remove(&pos) {
+            Some(index) => index,
+            None => return None,
+        };
+        let monster = self.monsters.swap_remove(index);
+        let reindexed = if index < self.monsters.len() {
+            let moved_monster = &self.monsters[index];
+            // NOTE: dead monsters are not on the level anymore so
+            // there's nothing to update there.
+            if moved_monster.alive() {
+                let moved_position = self.level.level_position(moved_monster.position - self.position);
+                self.level.set_monster(moved_position, index);
+            }
+            Some((moved_monster.id, index))
+        } else {
+            None
+        };
+        Some((monster, reindexed))
     }
 
-    pub fn world_position(&self, level_position: level::LevelPosition) -> Point {
-        let level_position: Point = level_position.into();
-        self.position + level_position
+    pub fn level_position(&self, world_position: Point) -> level::LevelPosition {
+        self.level.level_position(world_position - self.position)
     }
 
     pub fn cells(&self) -> ChunkCells {
@@ -118,11 +209,118 @@ struct ChunkPosition {
 }
 
 
+/// Contents of a `Chunk` as they're stored in the `ChunkCache`.
+#[derive(Serialize, Deserialize)]
+struct SerializedChunk {
+    position: Point,
+    size: i32,
+    cells: Vec<Cell>,
+    monsters: Vec<Monster>,
+}
+
+
+/// Keeps the chunks that are far away from the player on the disk.
+///
+/// The cache belongs to a single game. Its directory is removed when
+/// the cache is dropped.
+struct ChunkCache {
+    directory: PathBuf,
+    world_seed: u32,
+    stored: HashSet<ChunkPosition>,
+}
+
+impl ChunkCache {
+    /// Returns `None` if the cache directory can't be created.
+    fn new(directory: PathBuf, world_seed: u32) -> Option<Self> {
+        match fs::create_dir_all(&directory) {
+            Ok(()) => Some(ChunkCache {
+                directory,
+                world_seed,
+                stored: HashSet::new(),
+            }),
+            Err(_) => None,
+        }
+    }
+
+    fn path(&self, chunk_position: ChunkPosition) -> PathBuf {
+        let pos = chunk_position.position;
+        self.directory.join(format!("chunk_{}_{}.json", pos.x, pos.y))
+    }
+
+    /// Write the chunk to the disk. If that fails, the chunk is
+    /// returned back.
+    fn store(&mut self, chunk_position: ChunkPosition, chunk: Chunk) -> Result<(), Chunk> {
+        let path = self.path(chunk_position);
+        let serialized = chunk.into_serialized();
+        let json = serde_json::to_string(&serialized).expect(&format!(
+            "Could not serialise the chunk at {:?} to json.",
+            chunk_position
+        ));
+        let result = File::create(&path).and_then(|mut file| file.write_all(json.as_bytes()));
+        match result {
+            Ok(()) => {
+                self.stored.insert(chunk_position);
+                Ok(())
+            }
+            Err(_) => {
+                let _ = fs::remove_file(&path);
+                Err(Chunk::from_serialized(self.world_seed, serialized))
+            }
+        }
+    }
+
+    /// Load the chunk from the disk. Returns `None` if the chunk was
+    /// never stored.
+    fn load(&mut self, chunk_position: ChunkPosition) -> Option<Chunk> {
+        if !self.stored.remove(&chunk_position) {
+            return None;
+        }
+        let path = self.path(chunk_position);
+        let mut json = String::new();
+        File::open(&path)
+            .and_then(|mut file| file.read_to_string(&mut json))
+            .expect(&format!("Could not read the chunk from '{}'.", path.display()));
+        let serialized = serde_json::from_str(&json).expect(&format!(
+            "Could not parse the chunk stored in '{}'.",
+            path.display()
+        ));
+        let _ = fs::remove_file(&path);
+        Some(Chunk::from_serialized(self.world_seed, serialized))
+    }
+}
+
+impl Drop for ChunkCache {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+
 pub struct World {
     seed: u32,
     max_half_size: i32,
     chunk_size: i32,
+    initial_player_position: Point,
+    generator: Generator,
+    /// How many monsters were added by `spawn_monster`.
+    spawned_monsters: usize,
     chunks: HashMap<ChunkPosition, Chunk>,
+    /// The chunk and the index into its `monsters` for every monster
+    /// in the loaded chunks.
+    monster_index: HashMap<MonsterId, (ChunkPosition, usize)>,
+    /// Chunks far away from the player are stored here.
+    chunk_cache: Option<ChunkCache>,
+    /// The unloaded chunks that are kept in memory because there's no
+    /// chunk cache or it failed to store them. They're unloaded all
+    /// the same so the game plays out the same either way.
+    unloaded_chunks: HashMap<ChunkPosition, Chunk>,
+    /// Set when the chunk cache fails to store a chunk. We don't try
+    /// to store any more after that.
+    chunk_cache_failed: bool,
+    /// Monsters the new chunks are populated with.
+    monster_archetypes: MonsterArchetypes,
+    /// Items the new chunks are populated with.
+    item_archetypes: ItemArchetypes,
 }
 
 impl World {
@@ -132,6 +330,11 @@ impl World {
         dimension: i32,
         chunk_size: i32,
         initial_player_position: Point,
+        generator: Generator,
+        chunk_cache_directory: Option<PathBuf>,
+        monster_archetypes: MonsterArchetypes,
+        item_archetypes: ItemArchetypes,
+        balance: &Balance,
     ) -> Self {
         assert!(dimension > 0);
         assert!(chunk_size > 0);
@@ -142,12 +345,35 @@ impl World {
             seed,
             max_half_size: dimension / 2,
             chunk_size,
+            initial_player_position,
+            generator,
+            spawned_monsters: 0,
             chunks: HashMap::new(),
+            monster_index: HashMap::new(),
+            chunk_cache: chunk_cache_directory.and_then(|dir| ChunkCache::new(dir, seed)),
+            unloaded_chunks: HashMap::new(),
+            chunk_cache_failed: false,
+            monster_archetypes,
+            item_archetypes,
         };
 
         // TODO: I don't think this code belongs in World. Move it
         // into the level generators or osmething?
-        world.prepare_initial_playing_area(initial_player_position, rng);
+        match generator {
+            Generator::Forrest => {
+                world.prepare_initial_playing_area(initial_player_position, rng, balance);
+            }
+            // NOTE: the tutorial map is laid out by hand already
+            Generator::Tutorial => {
+                let easy_area = Rectangle::center(
+                    initial_player_position,
+                    Point::from_i32(balance.initial_easy_radius),
+                );
+                for pos in easy_area.points() {
+                    world.ensure_chunk_at_pos(pos);
+                }
+            }
+        }
         world
     }
 
@@ -157,18 +383,19 @@ impl World {
         &mut self,
         initial_player_position: Point,
         rng: &mut R,
+        balance: &Balance,
     ) {
-        assert!(formula::INITIAL_SAFE_RADIUS <= formula::INITIAL_EASY_RADIUS);
+        assert!(balance.initial_safe_radius <= balance.initial_easy_radius);
 
 
         let safe_area = Rectangle::center(
             initial_player_position,
-            Point::from_i32(formula::INITIAL_SAFE_RADIUS),
+            Point::from_i32(balance.initial_safe_radius),
         );
 
         let easy_area = Rectangle::center(
             initial_player_position,
-            Point::from_i32(formula::INITIAL_EASY_RADIUS),
+            Point::from_i32(balance.initial_easy_radius),
         );
 
         for pos in easy_area.points() {
@@ -193,18 +420,21 @@ impl World {
         // Remove strong doses from the starting area
         let no_lethal_dose_area = Rectangle::center(
             initial_player_position,
-            Point::from_i32(formula::NO_LETHAL_DOSE_RADIUS),
+            Point::from_i32(balance.no_lethal_dose_radius),
         );
 
+        let lethal_doses: Vec<item::Kind> = {
+            let archetypes = &self.item_archetypes;
+            archetypes
+                .kinds()
+                .filter(|&kind| archetypes.archetype(kind).excluded_from_start)
+                .collect()
+        };
+
         for pos in no_lethal_dose_area.points() {
             if let Some(cell) = self.cell_mut(pos) {
                 for index in (0..cell.items.len()).rev() {
-                    use item::Kind::*;
-                    let lethal_dose = match cell.items[index].kind {
-                        Food | Dose => false,
-                        StrongDose | CardinalDose | DiagonalDose => true,
-                    };
-                    if lethal_dose {
+                    if lethal_doses.contains(&cell.items[index].kind) {
                         cell.items.remove(index);
                     }
                 }
@@ -227,14 +457,13 @@ impl World {
             }
             let pos = initial_player_position + offset;
             if self.walkable(pos, Blocker::WALL, initial_player_position) {
-                let dose = Item {
-                    kind: item::Kind::Dose,
-                    modifier: player::Modifier::Intoxication {
-                        state_of_mind: 70,
-                        tolerance_increase: 1,
-                    },
-                    irresistible: 2,
+                // NOTE: the starting dose is weaker than the generated ones
+                let mut dose = self.item_archetypes.item(self.item_archetypes.starting_dose());
+                dose.modifier = player::Modifier::Intoxication {
+                    state_of_mind: 70,
+                    tolerance_increase: 1,
                 };
+                dose.explosion.radius = 4;
                 if let Some(chunk) = self.chunk_mut(pos) {
                     let level_position = chunk.level_position(pos);
                     chunk.level.add_item(level_position, dose);
@@ -252,13 +481,10 @@ impl World {
             };
             let pos = initial_player_position + offset;
             if self.walkable(pos, Blocker::WALL, initial_player_position) {
-                let food = Item {
-                    kind: item::Kind::Food,
-                    modifier: player::Modifier::Attribute {
-                        state_of_mind: 10,
-                        will: 0,
-                    },
-                    irresistible: 0,
+                let mut food = self.item_archetypes.item(self.item_archetypes.food());
+                food.modifier = player::Modifier::Attribute {
+                    state_of_mind: 10,
+                    will: 0,
                 };
                 if let Some(chunk) = self.chunk_mut(pos) {
                     let level_position = chunk.level_position(pos);
@@ -302,6 +528,13 @@ impl World {
     /// Get the chunk at the given world position. This means it
     /// doesn't have to match chunk's internal position -- any point
     /// within that Chunk will do.
+    ///
+    /// NOTE: unlike `chunk_mut`, this doesn't load the chunk back from
+    /// the chunk cache: it returns `None` for an unloaded chunk. So
+    /// do `cell`, `walkable` and everything else that takes `&self`.
+    /// The game keeps everything around the player loaded (see
+    /// `ensure_chunk_at_pos` and `unload_chunks_outside`) so this
+    /// only matters for the positions further away.
     pub fn chunk(&self, pos: Point) -> Option<&Chunk> {
         let chunk_position = self.chunk_pos_from_world_pos(pos);
         self.chunks.get(&chunk_position)
@@ -310,24 +543,108 @@ impl World {
     /// Get the mutable chunk at the given world position. This means
     /// it doesn't have to match chunk's internal position -- any
     /// point within that Chunk will do.
+    ///
+    /// If the chunk was unloaded, it will be loaded back.
     pub fn chunk_mut(&mut self, pos: Point) -> Option<&mut Chunk> {
         let chunk_position = self.chunk_pos_from_world_pos(pos);
+        self.reload_chunk(chunk_position);
         self.chunks.get_mut(&chunk_position)
     }
 
+    /// Make sure the chunk containing the given position is
+    /// available. It's either loaded from the chunk cache or
+    /// generated anew.
     pub fn ensure_chunk_at_pos(&mut self, pos: Point) {
         let chunk_position = self.chunk_pos_from_world_pos(pos);
+        self.reload_chunk(chunk_position);
 
         let seed = self.seed;
         let chunk_size = self.chunk_size;
+        let player_position = self.initial_player_position;
+        let generator = self.generator;
         // TODO: figure out how to generate the starting chunks so the
         // player has some doses and food and no monsters.
-        self.chunks.entry(chunk_position).or_insert_with(|| {
-            Chunk::new(seed, chunk_position, chunk_size, (0, 0).into())
-        });
+        if !self.chunks.contains_key(&chunk_position) {
+            let chunk = Chunk::new(
+                seed,
+                chunk_position,
+                chunk_size,
+                player_position,
+                generator,
+                &self.monster_archetypes,
+                &self.item_archetypes,
+            );
+            self.insert_chunk(chunk_position, chunk);
+        }
+    }
+
+    /// Load the chunk from the cache if it was unloaded before.
+    fn reload_chunk(&mut self, chunk_position: ChunkPosition) {
+        if self.chunks.contains_key(&chunk_position) {
+            return;
+        }
+        let cached_chunk = match self.unloaded_chunks.remove(&chunk_position) {
+            Some(chunk) => Some(chunk),
+            None => match self.chunk_cache {
+                Some(ref mut cache) => cache.load(chunk_position),
+                None => None,
+            },
+        };
+        if let Some(chunk) = cached_chunk {
+            self.insert_chunk(chunk_position, chunk);
+        }
+    }
+
+    fn insert_chunk(&mut self, chunk_position: ChunkPosition, chunk: Chunk) {
+        for (index, monster) in chunk.monsters().enumerate() {
+            self.monster_index.insert(monster.id, (chunk_position, index));
+        }
+        self.chunks.insert(chunk_position, chunk);
     }
 
-    fn cell(&self, world_pos: Point) -> Option<&Cell> {
+    /// Store all chunks that don't intersect the given area in the
+    /// chunk cache and remove them from memory.
+    ///
+    /// If the World doesn't have a chunk cache, the chunks are
+    /// unloaded all the same, they're just kept in memory. Returns
+    /// `true` if the chunk cache has just failed to store a chunk.
+    /// From then on, it's not used for storing anymore.
+    pub fn unload_chunks_outside(&mut self, area: Rectangle) -> bool {
+        let mut failed = false;
+        let chunk_size = self.chunk_size;
+        let distant_chunks = self.chunks
+            .keys()
+            .filter(|pos| {
+                let chunk_area = Rectangle::from_point_and_size(
+                    pos.position, Point::from_i32(chunk_size));
+                !area.intersects(chunk_area)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for chunk_position in distant_chunks {
+            if let Some(chunk) = self.chunks.remove(&chunk_position) {
+                for monster in chunk.monsters() {
+                    self.monster_index.remove(&monster.id);
+                }
+                let storing = self.chunk_cache.is_some() && !self.chunk_cache_failed;
+                let unstored_chunk = match self.chunk_cache {
+                    Some(ref mut cache) if storing => cache.store(chunk_position, chunk).err(),
+                    _ => Some(chunk),
+                };
+                if storing && unstored_chunk.is_some() {
+                    self.chunk_cache_failed = true;
+                    failed = true;
+                }
+                if let Some(chunk) = unstored_chunk {
+                    self.unloaded_chunks.insert(chunk_position, chunk);
+                }
+            }
+        }
+        failed
+    }
+
+    pub fn cell(&self, world_pos: Point) -> Option<&Cell> {
         let chunk = self.chunk(world_pos);
         // NOTE: the positions within a chunk/level start from zero so
         // we need to de-offset them with the chunk position.
@@ -384,6 +701,15 @@ impl World {
         }
     }
 
+    /// Pick up the equipment lying on the tile (if any).
+    pub fn pickup_equipment(&mut self, pos: Point) -> Option<Equipment> {
+        if self.within_bounds(pos) {
+            self.cell_mut(pos).and_then(|cell| cell.equipment.take())
+        } else {
+            None
+        }
+    }
+
     /// If there's a monster at the given tile, return its ID.
     ///
     /// Returns `None` if there is no monster or if `pos` is out of bounds.
@@ -402,6 +728,39 @@ impl World {
         }
     }
 
+    /// All the monsters that can appear in the world.
+    pub fn monster_archetypes(&self) -> &MonsterArchetypes {
+        &self.monster_archetypes
+    }
+
+    /// All the items that can appear in the world.
+    pub fn item_archetypes(&self) -> &ItemArchetypes {
+        &self.item_archetypes
+    }
+
+    /// Return the monster with the given ID.
+    ///
+    /// Returns `None` if the monster is not in any of the loaded
+    /// chunks. Dead monsters are returned as well.
+    pub fn monster(&self, id: MonsterId) -> Option<&Monster> {
+        self.monster_index.get(&id).and_then(|&(chunk_position, index)| {
+            self.chunks.get(&chunk_position).map(|chunk| &chunk.monsters[index])
+        })
+    }
+
+    /// Return the mutable monster with the given ID.
+    ///
+    /// Returns `None` if the monster is not in any of the loaded
+    /// chunks. Dead monsters are returned as well.
+    pub fn monster_mut(&mut self, id: MonsterId) -> Option<&mut Monster> {
+        match self.monster_index.get(&id) {
+            Some(&(chunk_position, index)) => {
+                self.chunks.get_mut(&chunk_position).map(|chunk| &mut chunk.monsters[index])
+            }
+            None => None,
+        }
+    }
+
     /// Move the monster from one place in the world to the destination.
     /// If the paths are identical, nothing happens.
     /// Panics if the destination is out of bounds or already occupied.
@@ -438,40 +797,38 @@ impl World {
                 level_destination_pos,
             );
         } else {
-            // Need to move the monster to another chunk
-            // NOTE: We're not removing the monster from the
-            // `chunk.monsters` vec in order not to mess up with the
-            // indices there.
-            //
-            // Instead, we make it dead here (without any of the
-            // normal connotations) and just remove it from the level.
-            let mut new_monster = {
-                let monster = self.monster_on_pos(monster_position).expect(
+            // Need to move the monster to another chunk. It's removed
+            // from the original chunk's `monsters` and given over to
+            // the destination chunk.
+            let (mut monster, reindexed) = {
+                let chunk = self.chunk_mut(monster_position).expect(&format!(
+                    "Chunk with monster {:?} doesn't exist.",
+                    monster_position
+                ));
+                let level_position = chunk.level_position(monster_position);
+                chunk.take_monster(level_position).expect(
                     "Trying to move a monster, but there's nothing \
                              there.",
-                );
-                let result = monster.clone();
-                monster.dead = true;
-                result
+                )
             };
+            if let Some((reindexed_id, new_index)) = reindexed {
+                self.monster_index.insert(reindexed_id, (monster_chunk_pos, new_index));
+            }
+            assert!(self.walkable(monster_position, blocker, player_position));
 
-            {
-                self.remove_monster(monster_position);
-                assert!(self.walkable(monster_position, blocker, player_position));
-                new_monster.position = destination;
-                let destination_chunk = self.chunk_mut(destination).expect(&format!(
+            monster.position = destination;
+            let monster_id = monster.id;
+            let new_monster_index = self.chunk_mut(destination)
+                .expect(&format!(
                     "Destination chunk at {:?} doesn't \
                                       exist.",
                     destination
-                ));
-                let new_monster_index = destination_chunk.monsters.len();
-                destination_chunk.monsters.push(new_monster);
-                let destination_level_position = destination_chunk.level_position(destination);
-                destination_chunk.level.set_monster(
-                    destination_level_position,
-                    new_monster_index,
-                );
-            }
+                ))
+                .add_monster(monster);
+            self.monster_index.insert(
+                monster_id,
+                (destination_chunk_pos, new_monster_index),
+            );
 
             assert!(!self.walkable(destination, Blocker::MONSTER, player_position));
         }
@@ -491,6 +848,36 @@ impl World {
         }
     }
 
+    /// Place a new monster of the given kind to the world position.
+    /// Returns its ID or `None` if the position is not walkable.
+    pub fn spawn_monster(&mut self, kind: monster::Kind, pos: Point, player_pos: Point) -> Option<MonsterId> {
+        let blocker = Blocker::PLAYER | Blocker::MONSTER | Blocker::WALL;
+        if !self.walkable(pos, blocker, player_pos) {
+            return None;
+        }
+        // NOTE: generated monsters have indices below the chunk's
+        // tile count (there's at most one on each tile) so starting
+        // past that keeps the spawned monsters' IDs unique.
+        let chunk_position = self.chunk_pos_from_world_pos(pos);
+        let index = (self.chunk_size * self.chunk_size) as usize + self.spawned_monsters;
+        let id = MonsterId::new(chunk_position.position, index);
+        let monster = {
+            let archetype = self.monster_archetypes
+                .archetypes
+                .iter()
+                .find(|archetype| archetype.kind == kind)
+                .expect(&format!("There's no monster archetype of the kind {:?}.", kind));
+            Monster::new(id, archetype, pos)
+        };
+        let chunk_index = match self.chunk_mut(pos) {
+            Some(chunk) => chunk.add_monster(monster),
+            None => return None,
+        };
+        self.monster_index.insert(id, (chunk_position, chunk_index));
+        self.spawned_monsters += 1;
+        Some(id)
+    }
+
     /// Set cells within the given radius as explored.
     pub fn explore(&mut self, centre: Point, radius: i32) {
         for pos in CircularArea::new(centre, radius) {
@@ -504,8 +891,17 @@ impl World {
 
     /// Get a dose within the given radius that's nearest to the
     /// specified point.
-    pub fn nearest_dose(&mut self, centre: Point, radius: i32) -> Option<(Point, Item)> {
-        let mut doses = vec![];
+    pub fn nearest_dose(&self, centre: Point, radius: i32) -> Option<(Point, Item)> {
+        self.nearest_item(centre, radius, |item| item.is_dose())
+    }
+
+    /// Get an item matching the `predicate` within the given radius
+    /// that's nearest to the specified point.
+    pub fn nearest_item<F>(&self, centre: Point, radius: i32, predicate: F) -> Option<(Point, Item)>
+    where
+        F: Fn(&Item) -> bool,
+    {
+        let mut items = vec![];
         for pos in CircularArea::new(centre, radius) {
             // Make sure we don't go out of bounds with self.cell(pos):
             // NOTE: We're not checking for the player's position here so we'll just supply a
@@ -513,19 +909,19 @@ impl World {
             if !self.walkable(pos, Blocker::WALL, Point::new(0, 0)) {
                 continue;
             }
-            doses.extend(
+            items.extend(
                 self.cell(pos)
                     .map_or(vec![].iter(), |cell| cell.items.iter())
-                    .filter(|i| i.is_dose())
+                    .filter(|i| predicate(i))
                     .map(|&item| (pos, item)),
             );
         }
 
-        doses.pop().map(|dose| {
-            let mut result = dose;
-            for d in &doses {
-                if centre.tile_distance(d.0) < centre.tile_distance(result.0) {
-                    result = *d;
+        items.pop().map(|item| {
+            let mut result = item;
+            for i in &items {
+                if centre.tile_distance(i.0) < centre.tile_distance(result.0) {
+                    result = *i;
                 }
             }
             result
@@ -628,6 +1024,13 @@ impl World {
             .filter(move |m| m.alive() && area.contains(m.position))
     }
 
+    /// How many chunks were generated so far: both the loaded and the
+    /// unloaded ones.
+    pub fn chunk_count(&self) -> usize {
+        let cached_count = self.chunk_cache.as_ref().map_or(0, |cache| cache.stored.len());
+        self.chunks.len() + self.unloaded_chunks.len() + cached_count
+    }
+
     pub fn positions_of_all_chunks(&self) -> Vec<Point> {
         self.chunks
             .keys()
@@ -635,3 +1038,245 @@ impl World {
             .collect()
     }
 }
+
+
+/// A forrest world without a chunk cache with the chunks covering the
+/// `area` loaded. The player starts at `(0, 0)`.
+#[cfg(test)]
+pub fn test_world(seed: u32, area: Rectangle) -> World {
+    new_test_world(seed, Generator::Forrest, area)
+}
+
+/// Where the tests build their maps (e.g. in the
+/// `test_world_of_trees`). It's away from the player's starting
+/// position at `(0, 0)`.
+#[cfg(test)]
+pub const TEST_ORIGIN: Point = Point { x: 100, y: 100 };
+
+/// A world with nothing but trees in the `area`: no monsters, no
+/// items. The tests carve out the tiles they need.
+#[cfg(test)]
+pub fn test_world_of_trees(area: Rectangle) -> World {
+    use level::{Tile, TileKind};
+    let mut world = new_test_world(0, Generator::Forrest, area);
+    for pos in area.points() {
+        world.remove_monster(pos);
+        if let Some(cell) = world.cell_mut(pos) {
+            cell.tile = Tile::new(TileKind::Tree);
+            cell.items.clear();
+            cell.equipment = None;
+        }
+    }
+    world
+}
+
+#[cfg(test)]
+fn new_test_world(seed: u32, generator: Generator, area: Rectangle) -> World {
+    let rng_seed: &[_] = &[seed];
+    let mut rng: IsaacRng = SeedableRng::from_seed(rng_seed);
+    let mut world = World::new(
+        &mut rng,
+        seed,
+        1024,
+        32,
+        Point::new(0, 0),
+        generator,
+        None,
+        MonsterArchetypes::load(),
+        ItemArchetypes::load(),
+        &Balance::default(),
+    );
+    for pos in area.points() {
+        world.ensure_chunk_at_pos(pos);
+    }
+    world
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Chunk, ChunkPosition, SerializedChunk, World};
+    use data::{ItemArchetypes, MonsterArchetypes};
+    use formula::Balance;
+    use generators::Generator;
+    use item;
+    use monster::MonsterId;
+    use point::Point;
+    use rand::{IsaacRng, SeedableRng};
+    use rect::Rectangle;
+    use serde_json;
+    use std::env;
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+
+    const SEED: u32 = 7;
+    const CHUNK_SIZE: i32 = 32;
+
+    fn cache_directory(test_name: &str) -> PathBuf {
+        env::temp_dir()
+            .join("dose-response")
+            .join(format!("test-{}", test_name))
+    }
+
+    fn world_with_cache(test_name: &str) -> World {
+        new_world(Some(cache_directory(test_name)))
+    }
+
+    fn new_world(chunk_cache_directory: Option<PathBuf>) -> World {
+        let rng_seed: &[_] = &[SEED];
+        let mut rng: IsaacRng = SeedableRng::from_seed(rng_seed);
+        World::new(
+            &mut rng,
+            SEED,
+            1024,
+            CHUNK_SIZE,
+            Point::new(0, 0),
+            Generator::Forrest,
+            chunk_cache_directory,
+            MonsterArchetypes::load(),
+            ItemArchetypes::load(),
+            &Balance::default(),
+        )
+    }
+
+    fn far_away_chunk() -> Rectangle {
+        Rectangle::from_point_and_size(Point::new(6 * CHUNK_SIZE, 0), Point::from_i32(CHUNK_SIZE))
+    }
+
+    fn items(world: &World, area: Rectangle) -> Vec<(Point, Vec<item::Kind>)> {
+        area.points()
+            .filter_map(|pos| {
+                world.cell(pos).map(|cell| (pos, cell.items.iter().map(|item| item.kind).collect()))
+            })
+            .collect()
+    }
+
+    fn monsters(world: &World, area: Rectangle) -> Vec<(MonsterId, Point)> {
+        let mut result: Vec<_> = world.monsters(area).map(|m| (m.id, m.position)).collect();
+        result.sort_by_key(|&(id, _)| id);
+        result
+    }
+
+    fn loaded_area() -> Rectangle {
+        Rectangle::center(Point::new(0, 0), Point::from_i32(50))
+    }
+
+    /// Generate the far away chunk and unload it again. Returns what
+    /// `unload_chunks_outside` did.
+    fn visit_far_away_chunk(world: &mut World) -> bool {
+        for pos in far_away_chunk().points() {
+            world.ensure_chunk_at_pos(pos);
+        }
+        world.unload_chunks_outside(loaded_area())
+    }
+
+    fn loaded_chunks(world: &World) -> Vec<Point> {
+        let mut result = world.positions_of_all_chunks();
+        result.sort_by_key(|pos| (pos.x, pos.y));
+        result
+    }
+
+    #[test]
+    fn serialized_chunk_round_trip() {
+        let position = ChunkPosition { position: Point::new(CHUNK_SIZE, 0) };
+        let chunk = Chunk::new(
+            SEED,
+            position,
+            CHUNK_SIZE,
+            Point::new(0, 0),
+            Generator::Forrest,
+            &MonsterArchetypes::load(),
+            &ItemArchetypes::load(),
+        );
+        let cells_before = format!("{:?}", chunk.cells().collect::<Vec<_>>());
+        let monsters_before: Vec<_> = chunk.monsters().cloned().collect();
+
+        let json = serde_json::to_string(&chunk.into_serialized()).unwrap();
+        let serialized: SerializedChunk = serde_json::from_str(&json).unwrap();
+        let chunk = Chunk::from_serialized(SEED, serialized);
+
+        assert_eq!(format!("{:?}", chunk.cells().collect::<Vec<_>>()), cells_before);
+        assert_eq!(chunk.monsters().cloned().collect::<Vec<_>>(), monsters_before);
+    }
+
+    #[test]
+    fn unloaded_chunks_keep_their_changes() {
+        let mut world = world_with_cache("unloaded-chunks-keep-their-changes");
+        let area = far_away_chunk();
+        for pos in area.points() {
+            world.ensure_chunk_at_pos(pos);
+        }
+
+        let explored_pos = area.top_left();
+        world.explore(explored_pos, 2);
+        let item_pos = area.points()
+            .find(|&pos| world.cell(pos).map_or(false, |cell| !cell.items.is_empty()))
+            .expect("The chunk should have some items.");
+        world.pickup_item(item_pos);
+        let (killed_id, killed_pos) = *monsters(&world, area)
+            .first()
+            .expect("The chunk should have some monsters.");
+        world.remove_monster(killed_pos);
+        let items_before = items(&world, area);
+        let monsters_before = monsters(&world, area);
+
+        world.unload_chunks_outside(Rectangle::center(Point::new(0, 0), Point::from_i32(50)));
+        assert!(world.chunk(explored_pos).is_none());
+        assert!(world.cell(explored_pos).is_none());
+
+        world.ensure_chunk_at_pos(explored_pos);
+        assert!(world.cell(explored_pos).unwrap().explored);
+        assert_eq!(items(&world, area), items_before);
+        assert_eq!(monsters(&world, area), monsters_before);
+        assert!(world.monster(killed_id).is_none());
+    }
+
+    #[test]
+    fn unloading_doesnt_depend_on_the_cache() {
+        let mut cached = world_with_cache("unloading-doesnt-depend-on-the-cache");
+        let mut uncached = new_world(None);
+        assert!(!visit_far_away_chunk(&mut cached));
+        assert!(!visit_far_away_chunk(&mut uncached));
+
+        assert!(uncached.chunk(far_away_chunk().top_left()).is_none());
+        assert_eq!(loaded_chunks(&uncached), loaded_chunks(&cached));
+        assert_eq!(uncached.chunk_count(), cached.chunk_count());
+        assert!(uncached.chunk_count() > uncached.positions_of_all_chunks().len());
+
+        uncached.ensure_chunk_at_pos(far_away_chunk().top_left());
+        cached.ensure_chunk_at_pos(far_away_chunk().top_left());
+        assert_eq!(monsters(&uncached, far_away_chunk()), monsters(&cached, far_away_chunk()));
+        assert_eq!(uncached.chunk_count(), cached.chunk_count());
+    }
+
+    #[test]
+    fn unusable_cache_directory_is_skipped() {
+        let file_path = cache_directory("unusable-cache-directory-is-skipped");
+        fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        File::create(&file_path).unwrap();
+        let mut world = new_world(Some(file_path.join("chunks")));
+        let mut uncached = new_world(None);
+        assert!(!visit_far_away_chunk(&mut world));
+        assert!(!visit_far_away_chunk(&mut uncached));
+        assert_eq!(world.chunk_count(), uncached.chunk_count());
+        let _ = fs::remove_file(&file_path);
+    }
+
+    #[test]
+    fn failing_cache_keeps_the_chunks_in_memory() {
+        let test_name = "failing-cache-keeps-the-chunks-in-memory";
+        let mut world = world_with_cache(test_name);
+        let mut uncached = new_world(None);
+        fs::remove_dir_all(cache_directory(test_name)).unwrap();
+
+        assert!(visit_far_away_chunk(&mut world));
+        assert!(!visit_far_away_chunk(&mut uncached));
+        assert_eq!(loaded_chunks(&world), loaded_chunks(&uncached));
+        assert_eq!(world.chunk_count(), uncached.chunk_count());
+
+        world.ensure_chunk_at_pos(far_away_chunk().top_left());
+        assert_eq!(monsters(&world, far_away_chunk()), monsters(&uncached, far_away_chunk()));
+        // NOTE: the failure is only reported once.
+        assert!(!world.unload_chunks_outside(loaded_area()));
+    }
+}